@@ -0,0 +1,203 @@
+use std::sync::Arc;
+
+use log::{debug, warn};
+
+use crate::api::lyric_error::LyricError;
+use crate::model::data_model::{Lyric, SongMetadata};
+use crate::store::lookup_cache::{CacheKey, CacheTtl, CachedAnswer, CachedLookup, LookupCache};
+use crate::store::outcome_store::now;
+use crate::traits::traits::LyricIface;
+
+/// Answers lookups from a [`LookupCache`] while they are fresh and only asks
+/// the wrapped provider otherwise. Lyrics, instrumental tracks and misses are
+/// cached; transient failures are not. A broken cache never fails a lookup.
+pub struct CachedProvider {
+    inner: Box<dyn LyricIface>,
+    cache: Arc<LookupCache>,
+    ttl: CacheTtl,
+    variant: String,
+}
+
+impl CachedProvider {
+    pub fn new(inner: Box<dyn LyricIface>, cache: Arc<LookupCache>) -> Self {
+        CachedProvider {
+            inner,
+            cache,
+            ttl: CacheTtl::default(),
+            variant: String::new(),
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: CacheTtl) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Keeps answers of differently configured instances of the same
+    /// provider apart, e.g. `synced` and `plain`.
+    pub fn with_variant(mut self, variant: &str) -> Self {
+        self.variant = variant.to_owned();
+        self
+    }
+
+    fn cached(&self, key: &CacheKey) -> Option<CachedAnswer> {
+        match self.cache.get(key) {
+            Ok(Some(lookup)) if lookup.is_fresh(&self.ttl, now()) => Some(lookup.answer),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("Failed to read the lookup cache: {}", e);
+                None
+            }
+        }
+    }
+
+    fn store(&self, key: &CacheKey, answer: CachedAnswer) {
+        let ttl = if answer.is_hit() { self.ttl.hit } else { self.ttl.miss };
+        if ttl.is_zero() {
+            return;
+        }
+        if let Err(e) = self.cache.put(key, &CachedLookup::new(answer)) {
+            warn!("Failed to update the lookup cache: {}", e);
+        }
+    }
+}
+
+impl LyricIface for CachedProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn base_url(&self) -> &str {
+        self.inner.base_url()
+    }
+
+    fn fetch_lyrics(&self, song_metadata: &SongMetadata) -> Result<Lyric, LyricError> {
+        let key = CacheKey::new(self.inner.name(), &self.variant, song_metadata).with_base_url(self.inner.base_url());
+        if let Some(answer) = self.cached(&key) {
+            debug!("Cached {} answer for {}", self.inner.name(), &song_metadata.title);
+            return match answer {
+                CachedAnswer::Found { lyric } => Ok(Lyric {
+                    lyric,
+                    song: song_metadata.song.clone(),
+                }),
+                CachedAnswer::NotFound => Err(LyricError::NotFound),
+                CachedAnswer::Instrumental => Err(LyricError::Instrumental),
+            };
+        }
+
+        let result = self.inner.fetch_lyrics(song_metadata);
+        match &result {
+            Ok(lyric) => self.store(&key, CachedAnswer::Found { lyric: lyric.lyric.clone() }),
+            Err(LyricError::NotFound) => self.store(&key, CachedAnswer::NotFound),
+            Err(LyricError::Instrumental) => self.store(&key, CachedAnswer::Instrumental),
+            Err(_) => {}
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test_cached_provider {
+    use super::*;
+    use crate::model::data_model::{FieldSources, Song};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Answers by title and counts how often it was asked.
+    struct Counting {
+        calls: Arc<AtomicUsize>,
+        base_url: &'static str,
+    }
+
+    impl LyricIface for Counting {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn base_url(&self) -> &str {
+            self.base_url
+        }
+
+        fn fetch_lyrics(&self, song_metadata: &SongMetadata) -> Result<Lyric, LyricError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match song_metadata.title.as_str() {
+                "missing" => Err(LyricError::NotFound),
+                "down" => Err(LyricError::Network(String::from("down"))),
+                title => Ok(Lyric {
+                    lyric: format!("Lyric of {}", title),
+                    song: song_metadata.song.clone(),
+                }),
+            }
+        }
+    }
+
+    fn metadata(title: &str) -> SongMetadata {
+        SongMetadata {
+            song: Song::empty(),
+            artist: String::from("Artist"),
+            title: title.to_owned(),
+            album_title: String::new(),
+            duration: None,
+            sources: FieldSources::default(),
+        }
+    }
+
+    fn provider(dir: &std::path::Path, ttl: CacheTtl) -> (CachedProvider, Arc<AtomicUsize>) {
+        let cache = Arc::new(LookupCache::open(&dir.join(LookupCache::FILENAME)).unwrap());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let inner = Box::new(Counting { calls: calls.clone(), base_url: "http://public" });
+        (CachedProvider::new(inner, cache).with_ttl(ttl), calls)
+    }
+
+    #[test]
+    fn hits_and_misses_are_answered_from_the_cache() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let (provider, calls) = provider(tmp_dir.path(), CacheTtl::default());
+
+        for _ in 0..2 {
+            assert_eq!(provider.fetch_lyrics(&metadata("Song")).unwrap().lyric, "Lyric of Song");
+            assert_eq!(provider.fetch_lyrics(&metadata("missing")).unwrap_err(), LyricError::NotFound);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(provider.name(), "counting");
+    }
+
+    #[test]
+    fn transient_failures_are_not_cached() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let (provider, calls) = provider(tmp_dir.path(), CacheTtl::default());
+
+        for _ in 0..2 {
+            assert!(provider.fetch_lyrics(&metadata("down")).is_err());
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn mirrors_do_not_share_answers() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let cache = Arc::new(LookupCache::open(&tmp_dir.path().join(LookupCache::FILENAME)).unwrap());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let [public, mirror] = ["http://public", "http://mirror"].map(|base_url| {
+            CachedProvider::new(Box::new(Counting { calls: calls.clone(), base_url }), cache.clone())
+        });
+
+        assert_eq!(public.fetch_lyrics(&metadata("missing")).unwrap_err(), LyricError::NotFound);
+        assert_eq!(mirror.fetch_lyrics(&metadata("missing")).unwrap_err(), LyricError::NotFound);
+        assert_eq!(public.fetch_lyrics(&metadata("missing")).unwrap_err(), LyricError::NotFound);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(mirror.base_url(), "http://mirror");
+    }
+
+    #[test]
+    fn zero_ttl_disables_caching() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let ttl = CacheTtl { miss: Duration::ZERO, ..CacheTtl::default() };
+        let (provider, calls) = provider(tmp_dir.path(), ttl);
+
+        for _ in 0..2 {
+            let _ = provider.fetch_lyrics(&metadata("missing"));
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt::{Display, Formatter};
+
+use crate::model::data_model::SongMetadata;
+use crate::model::lrc::LrcDocument;
+
+/// The fields of a search result that are compared against the song tags.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate<'a> {
+    pub title: &'a str,
+    pub artist: &'a str,
+    pub duration: Option<f64>,
+}
+
+/// Per-field similarity in `[0, 1]` and their weighted total.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBreakdown {
+    pub title: f64,
+    pub artist: f64,
+    pub duration: f64,
+    pub total: f64,
+}
+
+impl Display for ScoreBreakdown {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "total {:.3} (title {:.3}, artist {:.3}, duration {:.3})",
+            self.total, self.title, self.artist, self.duration
+        )
+    }
+}
+
+/// Scores search candidates by normalized title/artist similarity and
+/// duration distance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandidateScorer {
+    pub title_weight: f64,
+    pub artist_weight: f64,
+    pub duration_weight: f64,
+    /// Durations closer than this (in seconds) score 1.
+    pub duration_tolerance: f64,
+    /// Durations further apart than this (in seconds) score 0.
+    pub duration_cutoff: f64,
+}
+
+impl Default for CandidateScorer {
+    fn default() -> Self {
+        CandidateScorer {
+            title_weight: 0.45,
+            artist_weight: 0.35,
+            duration_weight: 0.2,
+            duration_tolerance: LrcDocument::DURATION_TOLERANCE,
+            duration_cutoff: 20.0,
+        }
+    }
+}
+
+impl CandidateScorer {
+    pub fn score(&self, song_metadata: &SongMetadata, candidate: &Candidate) -> ScoreBreakdown {
+        let title = Self::similarity(&song_metadata.title, candidate.title);
+        let artist = Self::similarity(&song_metadata.artist, candidate.artist);
+        let duration = self.duration_score(song_metadata.duration, candidate.duration);
+        let weights = self.title_weight + self.artist_weight + self.duration_weight;
+        let total = (title * self.title_weight + artist * self.artist_weight + duration * self.duration_weight)
+            / weights;
+        ScoreBreakdown {
+            title,
+            artist,
+            duration,
+            total,
+        }
+    }
+
+    /// Unknown durations are neutral rather than penalized.
+    fn duration_score(&self, expected: Option<f64>, actual: Option<f64>) -> f64 {
+        let (Some(expected), Some(actual)) = (expected, actual) else {
+            return 0.5;
+        };
+        if expected <= 0.0 {
+            return 0.5;
+        }
+        let distance = (expected - actual).abs();
+        if distance <= self.duration_tolerance {
+            1.0
+        } else if distance >= self.duration_cutoff {
+            0.0
+        } else {
+            1.0 - (distance - self.duration_tolerance) / (self.duration_cutoff - self.duration_tolerance)
+        }
+    }
+
+    /// Lowercases and keeps only alphanumeric words separated by single spaces.
+    pub fn normalize(text: &str) -> String {
+        text.to_lowercase()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    /// `1 - levenshtein / longest length` over normalized strings.
+    pub fn similarity(left: &str, right: &str) -> f64 {
+        let left: Vec<char> = Self::normalize(left).chars().collect();
+        let right: Vec<char> = Self::normalize(right).chars().collect();
+        let longest = left.len().max(right.len());
+        if longest == 0 {
+            return 1.0;
+        }
+        1.0 - Self::levenshtein(&left, &right) as f64 / longest as f64
+    }
+
+    fn levenshtein(left: &[char], right: &[char]) -> usize {
+        let mut previous: Vec<usize> = (0..=right.len()).collect();
+        for (i, l) in left.iter().enumerate() {
+            let mut current = vec![i + 1; right.len() + 1];
+            for (j, r) in right.iter().enumerate() {
+                let substitution = previous[j] + usize::from(l != r);
+                current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+            }
+            previous = current;
+        }
+        previous[right.len()]
+    }
+}
+
+#[cfg(test)]
+mod test_candidate_scoring {
+    use super::*;
+    use crate::model::data_model::{FieldSources, Song};
+
+    fn metadata(artist: &str, title: &str, duration: Option<f64>) -> SongMetadata {
+        SongMetadata {
+            song: Song::empty(),
+            artist: artist.to_owned(),
+            title: title.to_owned(),
+            album_title: String::new(),
+            duration,
+            sources: FieldSources::default(),
+        }
+    }
+
+    #[test]
+    fn normalize_ignores_case_and_punctuation() {
+        assert_eq!(CandidateScorer::normalize("  Don't  STOP—Me Now! "), "don t stop me now");
+        assert_eq!(CandidateScorer::similarity("AC/DC", "ac dc"), 1.0);
+        assert_eq!(CandidateScorer::similarity("", ""), 1.0);
+        assert_eq!(CandidateScorer::similarity("abc", ""), 0.0);
+    }
+
+    #[test]
+    fn exact_match_scores_one() {
+        let scorer = CandidateScorer::default();
+        let md = metadata("Benny Blanco", "Roses", Some(223.0));
+        let candidate = Candidate { title: "Roses", artist: "benny blanco", duration: Some(222.4) };
+
+        let breakdown = scorer.score(&md, &candidate);
+        assert_eq!(breakdown.total, 1.0);
+    }
+
+    #[test]
+    fn spelling_differences_score_higher_than_other_songs() {
+        let scorer = CandidateScorer::default();
+        let md = metadata("Beyonce", "Halo", Some(261.0));
+        let close = Candidate { title: "Halo", artist: "Beyoncé", duration: Some(262.0) };
+        let other = Candidate { title: "Hello", artist: "Adele", duration: Some(295.0) };
+
+        let close = scorer.score(&md, &close);
+        let other = scorer.score(&md, &other);
+        assert!(close.total > 0.9, "{}", close);
+        assert!(other.total < 0.5, "{}", other);
+    }
+
+    #[test]
+    fn duration_distance_decays_linearly() {
+        let scorer = CandidateScorer::default();
+        assert_eq!(scorer.duration_score(Some(200.0), Some(202.0)), 1.0);
+        assert_eq!(scorer.duration_score(Some(200.0), Some(211.0)), 0.5);
+        assert_eq!(scorer.duration_score(Some(200.0), Some(230.0)), 0.0);
+        assert_eq!(scorer.duration_score(None, Some(230.0)), 0.5);
+    }
+}
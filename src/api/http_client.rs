@@ -0,0 +1,109 @@
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use reqwest::Proxy;
+
+use crate::api::lyric_error::LyricError;
+
+/// Used by providers that were not given a client of their own.
+static SHARED_CLIENT: LazyLock<Client> =
+    LazyLock::new(|| HttpOptions::default().build().expect("Failed to build the default HTTP client"));
+
+/// Settings of the HTTP client the providers share. One client keeps a
+/// connection pool, so a directory run reuses connections instead of opening
+/// a new one for every track.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpOptions {
+    pub connect_timeout: Duration,
+    /// Limit for a whole request, from connecting until the body is read.
+    pub timeout: Duration,
+    /// Sent to every provider; LRCLib asks clients to identify themselves.
+    pub user_agent: String,
+    /// Proxy for every request, e.g. `http://proxy:3128` or `socks5://host:1080`.
+    /// Without it the usual `HTTP_PROXY`/`HTTPS_PROXY` variables apply.
+    pub proxy: Option<String>,
+    /// How long an unused connection is kept open for the next request.
+    pub idle_timeout: Duration,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        HttpOptions {
+            connect_timeout: Self::DEFAULT_CONNECT_TIMEOUT,
+            timeout: Self::DEFAULT_TIMEOUT,
+            user_agent: Self::DEFAULT_USER_AGENT.to_owned(),
+            proxy: None,
+            idle_timeout: Self::DEFAULT_IDLE_TIMEOUT,
+        }
+    }
+}
+
+impl HttpOptions {
+    pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+    pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+    pub const DEFAULT_USER_AGENT: &'static str =
+        concat!("GetLyrics v", env!("CARGO_PKG_VERSION"), " (https://github.com/albertjimenez/GetLyrics)");
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_owned();
+        self
+    }
+
+    pub fn with_proxy(mut self, proxy: &str) -> Self {
+        self.proxy = Some(proxy.to_owned());
+        self
+    }
+
+    /// Builds a client with these settings. Clones of it share the same pool.
+    pub fn build(&self) -> Result<Client, LyricError> {
+        let mut builder = Client::builder()
+            .user_agent(&self.user_agent)
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.timeout)
+            .pool_idle_timeout(self.idle_timeout)
+            .tcp_keepalive(self.idle_timeout);
+        if let Some(proxy) = &self.proxy {
+            let proxy = Proxy::all(proxy).map_err(|e| LyricError::Network(format!("Invalid proxy {}: {}", proxy, e)))?;
+            builder = builder.proxy(proxy);
+        }
+        Ok(builder.build()?)
+    }
+
+    /// A process-wide client with the default settings.
+    pub fn shared_client() -> Client {
+        SHARED_CLIENT.clone()
+    }
+}
+
+#[cfg(test)]
+mod test_http_options {
+    use super::*;
+
+    #[test]
+    fn defaults_identify_the_crate() {
+        let options = HttpOptions::default();
+        assert!(options.user_agent.starts_with("GetLyrics v"));
+        assert_eq!(options.timeout, HttpOptions::DEFAULT_TIMEOUT);
+        assert_eq!(options.proxy, None);
+        assert!(options.build().is_ok());
+    }
+
+    #[test]
+    fn invalid_proxy_is_rejected() {
+        let options = HttpOptions::default().with_proxy("not a url");
+        assert!(matches!(options.build(), Err(LyricError::Network(_))));
+        assert!(HttpOptions::default().with_proxy("http://127.0.0.1:3128").build().is_ok());
+    }
+}
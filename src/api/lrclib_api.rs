@@ -1,72 +1,266 @@
-use log::warn;
+use log::{debug, info, warn};
 use reqwest::blocking::Client;
 use serde::Deserialize;
 
+use crate::api::candidate_scoring::{Candidate, CandidateScorer, ScoreBreakdown};
+use crate::api::http_client::HttpOptions;
+use crate::api::lyric_error::LyricError;
+use crate::api::query_normalizer::QueryNormalizer;
+use crate::api::throttle::Throttle;
 use crate::model::data_model::{Lyric, SongMetadata};
+use crate::model::lrc::LrcDocument;
 use crate::traits::traits::LyricIface;
 
 #[derive(Debug, Deserialize)]
 struct LrcLibResponse {
+    #[serde(default)]
+    instrumental: bool,
+    #[serde(rename = "trackName", default)]
+    track_name: String,
+    #[serde(rename = "artistName", default)]
+    artist_name: String,
+    #[serde(default)]
+    duration: Option<f64>,
     #[serde(rename = "plainLyrics")]
-    plain_lyrics: String,
+    plain_lyrics: Option<String>,
     #[serde(rename = "syncedLyrics")]
     synced_lyrics: Option<String>,
 }
 pub struct LrcLibAPI {
     karaoke: bool,
+    base_url: String,
+    search: bool,
+    search_threshold: f64,
+    duration_tolerance: f64,
+    scorer: CandidateScorer,
+    client: Client,
+    throttle: Throttle,
 }
 
 impl LrcLibAPI {
+    pub const NAME: &'static str = "lrclib";
+    pub const DEFAULT_BASE_URL: &'static str = "https://lrclib.net/api";
+    pub const DEFAULT_SEARCH_THRESHOLD: f64 = 0.75;
+
     pub fn new_karaoke_lyrics() -> Self {
-        LrcLibAPI { karaoke: true }
+        Self::new(true)
     }
     pub fn new_plain_lyrics() -> Self {
-        LrcLibAPI { karaoke: false }
+        Self::new(false)
     }
-}
-impl LyricIface for LrcLibAPI {
-    fn fetch_lyrics(&self, song_metadata: &SongMetadata) -> Result<Lyric, String> {
-        let base_url = "https://lrclib.net/api/get";
-        let params = [
+    fn new(karaoke: bool) -> Self {
+        LrcLibAPI {
+            karaoke,
+            base_url: Self::DEFAULT_BASE_URL.to_owned(),
+            search: true,
+            search_threshold: Self::DEFAULT_SEARCH_THRESHOLD,
+            duration_tolerance: LrcDocument::DURATION_TOLERANCE,
+            scorer: CandidateScorer::default(),
+            client: HttpOptions::shared_client(),
+            throttle: Throttle::default(),
+        }
+    }
+    /// Points the provider at a mirror, e.g. `http://localhost:3000/api`.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_owned();
+        self
+    }
+    /// Minimum [`ScoreBreakdown::total`] a search candidate needs to be accepted.
+    pub fn with_search_threshold(mut self, threshold: f64) -> Self {
+        self.search_threshold = threshold;
+        self
+    }
+    /// Seconds a search result's length may differ from the track's, and a
+    /// synced lyric may run past its end. Search results within it also get
+    /// the full duration score.
+    pub fn with_duration_tolerance(mut self, seconds: f64) -> Self {
+        self.duration_tolerance = seconds.max(0.0);
+        self.scorer.duration_tolerance = self.duration_tolerance;
+        self
+    }
+    /// Replaces the scorer, including its duration tolerance.
+    pub fn with_scorer(mut self, scorer: CandidateScorer) -> Self {
+        self.scorer = scorer;
+        self
+    }
+    /// Sends requests through `client`, usually the one shared by the whole chain.
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+    /// Rate limits and retries this provider's requests.
+    pub fn with_throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = throttle;
+        self
+    }
+    /// Only use the exact-match `/get` endpoint.
+    pub fn without_search(mut self) -> Self {
+        self.search = false;
+        self
+    }
+
+    fn get(&self, song_metadata: &SongMetadata) -> Result<Lyric, LyricError> {
+        let url = format!("{}/get", self.base_url);
+        // LRCLib matches whole seconds; an unknown length is left out rather than sent as 0.
+        let duration = song_metadata.duration.map(|seconds| seconds.round().to_string());
+        let mut params = vec![
             ("track_name", song_metadata.title.as_str()),
             ("artist_name", song_metadata.artist.as_str()),
             ("album_name", song_metadata.album_title.as_str()),
-            ("duration", &song_metadata.duration.unwrap_or(0).to_string()),
         ];
-        let client = Client::builder()
-            .user_agent("https://github.com/albertjimenez/GetLyrics")
-            .build()
-            .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+        if let Some(duration) = &duration {
+            params.push(("duration", duration.as_str()));
+        }
 
-        let response = client
-            .get(base_url)
-            .query(&params)
-            .send()
-            .map_err(|e| format!("Request error: {}", e))?;
+        let response = self.throttle.send(self.client.get(&url).query(&params))?;
 
         match response.status().as_u16() {
             200 => {
                 let data: LrcLibResponse = response
                     .json()
-                    .map_err(|e| format!("Failed to parse response JSON: {}", e))?;
-                let mut lyrics = data.plain_lyrics;
-                if data.synced_lyrics.is_none() && self.karaoke {
-                    warn!(
-                        "Falling back to traditional lyric since no synced lyric was found for {}",
-                        &song_metadata.title
+                    .map_err(|e| LyricError::Parse(e.to_string()))?;
+                self.to_lyric(data, song_metadata)
+            }
+            _ => Err(LyricError::from_status(&response)),
+        }
+    }
+
+    /// Queries `/search` and accepts the best scoring candidate above the threshold.
+    fn search(&self, song_metadata: &SongMetadata) -> Result<Lyric, LyricError> {
+        let url = format!("{}/search", self.base_url);
+        let mut params = vec![("track_name", song_metadata.title.as_str())];
+        if !song_metadata.artist.is_empty() {
+            params.push(("artist_name", song_metadata.artist.as_str()));
+        }
+
+        let response = self.throttle.send(self.client.get(&url).query(&params))?;
+        if !response.status().is_success() {
+            return Err(LyricError::from_status(&response));
+        }
+        let candidates: Vec<LrcLibResponse> = response
+            .json()
+            .map_err(|e| LyricError::Parse(e.to_string()))?;
+
+        let best = candidates
+            .into_iter()
+            .filter(|candidate| {
+                let fits = self.within_tolerance(song_metadata.duration, candidate.duration);
+                if !fits {
+                    debug!(
+                        "Skipping LRCLib candidate '{}' by '{}' ({:?}s) for '{}': length differs by more than {}s",
+                        candidate.track_name, candidate.artist_name, candidate.duration, &song_metadata.title,
+                        self.duration_tolerance
                     );
                 }
-                if data.synced_lyrics.is_some() && self.karaoke {
-                    lyrics = data.synced_lyrics.unwrap();
-                }
-                let lyric = Lyric {
-                    lyric: lyrics,
+                fits
+            })
+            .map(|candidate| {
+                let breakdown = self.score(song_metadata, &candidate);
+                debug!(
+                    "LRCLib candidate '{}' by '{}' ({:?}s) for '{}': {}",
+                    candidate.track_name, candidate.artist_name, candidate.duration, &song_metadata.title, breakdown
+                );
+                (candidate, breakdown)
+            })
+            .reduce(|best, next| if next.1.total > best.1.total { next } else { best });
+
+        match best {
+            Some((candidate, breakdown)) if breakdown.total >= self.search_threshold => {
+                info!(
+                    "Accepted LRCLib search result '{}' by '{}' for '{}': {}",
+                    candidate.track_name, candidate.artist_name, &song_metadata.title, breakdown
+                );
+                self.to_lyric(candidate, song_metadata)
+            }
+            Some((candidate, breakdown)) => {
+                info!(
+                    "Rejected best LRCLib search result '{}' by '{}' for '{}' below threshold {}: {}",
+                    candidate.track_name, candidate.artist_name, &song_metadata.title, self.search_threshold, breakdown
+                );
+                Err(LyricError::NotFound)
+            }
+            None => Err(LyricError::NotFound),
+        }
+    }
+
+    /// Unknown lengths on either side are accepted.
+    fn within_tolerance(&self, expected: Option<f64>, actual: Option<f64>) -> bool {
+        match (expected.filter(|seconds| *seconds > 0.0), actual) {
+            (Some(expected), Some(actual)) => (expected - actual).abs() <= self.duration_tolerance,
+            _ => true,
+        }
+    }
+
+    /// Whether the last line of a synced lyric starts before the track ends.
+    fn fits_track(&self, synced: &str, song_metadata: &SongMetadata) -> bool {
+        LrcDocument::parse(synced)
+            .runs_past(song_metadata.duration, self.duration_tolerance)
+            .is_none()
+    }
+
+    fn score(&self, song_metadata: &SongMetadata, candidate: &LrcLibResponse) -> ScoreBreakdown {
+        let candidate = Candidate {
+            title: &candidate.track_name,
+            artist: &candidate.artist_name,
+            duration: candidate.duration,
+        };
+        self.scorer.score(song_metadata, &candidate)
+    }
+
+    fn to_lyric(&self, data: LrcLibResponse, song_metadata: &SongMetadata) -> Result<Lyric, LyricError> {
+        if data.instrumental {
+            return Err(LyricError::Instrumental);
+        }
+        let rejected = data
+            .synced_lyrics
+            .as_ref()
+            .is_some_and(|synced| !self.fits_track(synced, song_metadata));
+        let synced = data.synced_lyrics.filter(|_| !rejected);
+        if self.karaoke {
+            if let Some(synced) = synced {
+                return Ok(Lyric {
+                    lyric: synced,
                     song: song_metadata.song.to_owned(),
-                };
-                Ok(lyric)
+                });
+            }
+            if rejected {
+                warn!(
+                    "Falling back to traditional lyric since the synced lyric for {} runs past the end of the track",
+                    &song_metadata.title
+                );
+            } else {
+                warn!(
+                    "Falling back to traditional lyric since no synced lyric was found for {}",
+                    &song_metadata.title
+                );
+            }
+        }
+        // Some answers only carry a synced lyric; it still beats nothing,
+        // unless it was rejected for running past the end of the track.
+        let lyrics = data.plain_lyrics.or(synced).ok_or(LyricError::NotFound)?;
+        Ok(Lyric {
+            lyric: lyrics,
+            song: song_metadata.song.to_owned(),
+        })
+    }
+}
+impl LyricIface for LrcLibAPI {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn fetch_lyrics(&self, song_metadata: &SongMetadata) -> Result<Lyric, LyricError> {
+        let variants = QueryNormalizer::variants(song_metadata);
+        match QueryNormalizer::first_match(&variants, |variant| self.get(variant)) {
+            Err(LyricError::NotFound) if self.search => {
+                debug!("No exact LRCLib match for {}, searching", &song_metadata.title);
+                QueryNormalizer::first_match(&variants, |variant| self.search(variant))
             }
-            404 => Err("Lyrics not found.".to_string()),
-            code => Err(format!("Unexpected status code: {}", code)),
+            result => result,
         }
     }
 }
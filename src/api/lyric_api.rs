@@ -1,8 +1,12 @@
-use std::fmt::format;
-
 use regex::Regex;
+use reqwest::blocking::Client;
+use reqwest::Url;
 use serde::Deserialize;
 
+use crate::api::http_client::HttpOptions;
+use crate::api::lyric_error::LyricError;
+use crate::api::query_normalizer::QueryNormalizer;
+use crate::api::throttle::Throttle;
 use crate::model::data_model::{Lyric, SongMetadata};
 use crate::traits::traits::LyricIface;
 
@@ -10,9 +14,16 @@ use crate::traits::traits::LyricIface;
 struct ApiResponse {
     lyrics: String,
 }
-pub struct LyricApi {}
+pub struct LyricApi {
+    base_url: String,
+    client: Client,
+    throttle: Throttle,
+}
 
 impl LyricApi {
+    pub const NAME: &'static str = "lyrics.ovh";
+    pub const DEFAULT_BASE_URL: &'static str = "https://api.lyrics.ovh/v1";
+
     fn sanitize_lyrics(lyrics: &str) -> String {
         // Replace \r\n and multiple \n with a single newline
         let newline_regex = Regex::new(r"\r\n|\n+").unwrap();
@@ -26,30 +37,73 @@ impl LyricApi {
         lyrics.trim().to_string()
     }
     pub fn new() -> Self {
-        LyricApi {}
+        LyricApi {
+            base_url: Self::DEFAULT_BASE_URL.to_owned(),
+            client: HttpOptions::shared_client(),
+            throttle: Throttle::default(),
+        }
     }
-}
-impl LyricIface for LyricApi {
-    fn fetch_lyrics(&self, song_metadata: &SongMetadata) -> Result<Lyric, String> {
-        let url = format(format_args!(
-            "https://api.lyrics.ovh/v1/{}/{}",
-            &song_metadata.artist, &song_metadata.title
-        ));
-        let response =
-            reqwest::blocking::get(&url).map_err(|e| format!("Network request failed: {}", e))?;
+    /// Points the provider at a mirror, e.g. `http://localhost:3000/v1`.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_owned();
+        self
+    }
+    /// Sends requests through `client`, usually the one shared by the whole chain.
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+    /// Rate limits and retries this provider's requests.
+    pub fn with_throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = throttle;
+        self
+    }
+    fn lyrics_url(&self, song_metadata: &SongMetadata) -> Result<Url, LyricError> {
+        let mut url = Url::parse(&self.base_url)
+            .map_err(|e| LyricError::Network(format!("Invalid base URL {}: {}", &self.base_url, e)))?;
+        url.path_segments_mut()
+            .map_err(|_| LyricError::Network(format!("Invalid base URL {}", &self.base_url)))?
+            .pop_if_empty()
+            .push(&song_metadata.artist)
+            .push(&song_metadata.title);
+        Ok(url)
+    }
+    fn fetch_variant(&self, song_metadata: &SongMetadata) -> Result<Lyric, LyricError> {
+        let url = self.lyrics_url(song_metadata)?;
+        let response = self.throttle.send(self.client.get(url))?;
 
         if !response.status().is_success() {
-            return Err("Lyric not found with LyricsAPI".to_owned());
-        } else {
-            let api_response: Result<ApiResponse, _> = response.json();
-            if api_response.is_ok() {
-                let lyrics = Self::sanitize_lyrics(&api_response.unwrap().lyrics);
-                return Ok(Lyric {
-                    lyric: lyrics,
-                    song: song_metadata.song.clone(),
-                });
-            }
+            return Err(LyricError::from_status(&response));
         }
-        Err("Lyric API is down".to_owned())
+        let api_response: ApiResponse = response
+            .json()
+            .map_err(|e| LyricError::Parse(e.to_string()))?;
+        let lyrics = Self::sanitize_lyrics(&api_response.lyrics);
+        if lyrics.is_empty() {
+            return Err(LyricError::NotFound);
+        }
+        Ok(Lyric {
+            lyric: lyrics,
+            song: song_metadata.song.clone(),
+        })
+    }
+}
+impl Default for LyricApi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl LyricIface for LyricApi {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn fetch_lyrics(&self, song_metadata: &SongMetadata) -> Result<Lyric, LyricError> {
+        let variants = QueryNormalizer::variants(song_metadata);
+        QueryNormalizer::first_match(&variants, |variant| self.fetch_variant(variant))
     }
 }
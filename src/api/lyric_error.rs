@@ -0,0 +1,120 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use reqwest::blocking::Response;
+use reqwest::header::RETRY_AFTER;
+
+/// Why a provider could not return a lyric.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LyricError {
+    /// The provider answered, but has no lyric for this track.
+    NotFound,
+    /// The request never got a usable answer (DNS, TLS, timeout, connection reset...).
+    Network(String),
+    /// The provider asked us to slow down.
+    RateLimited { retry_after: Option<Duration> },
+    /// The provider answered with something we could not understand.
+    Parse(String),
+    /// The provider answered with an unexpected HTTP status.
+    Upstream { status: u16 },
+    /// The provider knows the track and it has no lyrics.
+    Instrumental,
+}
+
+impl LyricError {
+    /// Whether a provider chain should try the next provider after this error.
+    /// An instrumental track is a definitive answer, so it stops the chain.
+    pub fn falls_through(&self) -> bool {
+        !matches!(self, LyricError::Instrumental)
+    }
+
+    /// Whether repeating the same request later may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LyricError::Network(_) | LyricError::RateLimited { .. } => true,
+            LyricError::Upstream { status } => *status >= 500,
+            LyricError::NotFound | LyricError::Parse(_) | LyricError::Instrumental => false,
+        }
+    }
+
+    /// Maps a non-success HTTP response to an error.
+    pub fn from_status(response: &Response) -> LyricError {
+        match response.status().as_u16() {
+            404 => LyricError::NotFound,
+            429 => LyricError::RateLimited {
+                retry_after: Self::retry_after(response),
+            },
+            status => LyricError::Upstream { status },
+        }
+    }
+
+    fn retry_after(response: &Response) -> Option<Duration> {
+        response
+            .headers()
+            .get(RETRY_AFTER)?
+            .to_str()
+            .ok()?
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .map(Duration::from_secs)
+    }
+}
+
+impl Display for LyricError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LyricError::NotFound => write!(f, "Lyrics not found."),
+            LyricError::Network(e) => write!(f, "Network error: {}", e),
+            LyricError::RateLimited { retry_after: Some(after) } => {
+                write!(f, "Rate limited, retry after {}s", after.as_secs())
+            }
+            LyricError::RateLimited { retry_after: None } => write!(f, "Rate limited"),
+            LyricError::Parse(e) => write!(f, "Failed to parse response: {}", e),
+            LyricError::Upstream { status } => write!(f, "Unexpected status code: {}", status),
+            LyricError::Instrumental => write!(f, "Track is instrumental."),
+        }
+    }
+}
+
+impl std::error::Error for LyricError {}
+
+impl From<reqwest::Error> for LyricError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            LyricError::Parse(e.to_string())
+        } else {
+            LyricError::Network(e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_lyric_error {
+    use super::*;
+
+    #[test]
+    fn only_instrumental_stops_the_chain() {
+        assert!(LyricError::NotFound.falls_through());
+        assert!(LyricError::Network("down".into()).falls_through());
+        assert!(LyricError::Upstream { status: 500 }.falls_through());
+        assert!(!LyricError::Instrumental.falls_through());
+    }
+
+    #[test]
+    fn retryable_errors() {
+        assert!(LyricError::Network("reset".into()).is_retryable());
+        assert!(LyricError::RateLimited { retry_after: None }.is_retryable());
+        assert!(LyricError::Upstream { status: 503 }.is_retryable());
+        assert!(!LyricError::Upstream { status: 400 }.is_retryable());
+        assert!(!LyricError::NotFound.is_retryable());
+        assert!(!LyricError::Parse("bad json".into()).is_retryable());
+    }
+
+    #[test]
+    fn display() {
+        let limited = LyricError::RateLimited { retry_after: Some(Duration::from_secs(30)) };
+        assert_eq!(limited.to_string(), "Rate limited, retry after 30s");
+        assert_eq!(LyricError::Upstream { status: 502 }.to_string(), "Unexpected status code: 502");
+    }
+}
@@ -1,2 +1,9 @@
+pub mod cached_provider;
+pub mod candidate_scoring;
+pub mod http_client;
 pub mod lyric_api;
-pub mod lrclib_api;
\ No newline at end of file
+pub mod lrclib_api;
+pub mod lyric_error;
+pub mod provider_chain;
+pub mod query_normalizer;
+pub mod throttle;
\ No newline at end of file
@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use log::{debug, info, warn};
+use reqwest::blocking::Client;
+
+use crate::api::cached_provider::CachedProvider;
+use crate::api::http_client::HttpOptions;
+use crate::api::lrclib_api::LrcLibAPI;
+use crate::api::lyric_api::LyricApi;
+use crate::api::lyric_error::LyricError;
+use crate::api::throttle::{Throttle, ThrottleOptions};
+use crate::model::data_model::{Lyric, SongMetadata};
+use crate::store::lookup_cache::{CacheTtl, LookupCache};
+use crate::traits::traits::LyricIface;
+
+/// How a [`ProviderChain`] combines the answers of its providers.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum ChainStrategy {
+    /// Ask providers in order and stop at the first one that returns a lyric.
+    FirstSuccess,
+    /// Ask every provider and keep the highest scoring lyric.
+    BestScore,
+    /// Ask every provider, keep the first lyric in chain order but upgrade it
+    /// to a synced lyric from a later provider when the first one is plain.
+    AllAndMerge,
+}
+
+impl ChainStrategy {
+    pub fn from_name(name: &str) -> Option<ChainStrategy> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "first" | "first-success" => Some(ChainStrategy::FirstSuccess),
+            "best" | "best-score" => Some(ChainStrategy::BestScore),
+            "merge" | "all-and-merge" => Some(ChainStrategy::AllAndMerge),
+            _ => None,
+        }
+    }
+}
+
+/// Settings shared by the public providers when a chain instantiates them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProviderOptions {
+    pub karaoke: bool,
+    /// Base URL overrides keyed by provider name.
+    pub base_urls: HashMap<String, String>,
+    /// Minimum score for LRCLib search results, `None` keeps the provider default.
+    pub search_threshold: Option<f64>,
+    /// Allowed track length difference in seconds, `None` keeps the provider default.
+    pub duration_tolerance: Option<f64>,
+    /// Settings of the HTTP client every provider of the chain shares.
+    pub http: HttpOptions,
+    /// Rate limit and retries of every provider without an override.
+    pub throttle: ThrottleOptions,
+    /// Rate limit and retry overrides keyed by provider name.
+    pub throttles: HashMap<String, ThrottleOptions>,
+}
+
+impl ProviderOptions {
+    pub fn new(karaoke: bool) -> Self {
+        ProviderOptions {
+            karaoke,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_base_url(&mut self, provider: &str, url: &str) {
+        self.base_urls.insert(provider.to_owned(), url.to_owned());
+    }
+
+    pub fn base_url(&self, provider: &str) -> Option<&str> {
+        self.base_urls.get(provider).map(String::as_str)
+    }
+
+    pub fn set_throttle(&mut self, provider: &str, throttle: ThrottleOptions) {
+        self.throttles.insert(provider.to_owned(), throttle);
+    }
+
+    pub fn throttle(&self, provider: &str) -> &ThrottleOptions {
+        self.throttles.get(provider).unwrap_or(&self.throttle)
+    }
+}
+
+/// A lyric together with the provider that produced it.
+#[derive(Debug, Clone)]
+pub struct ProviderLyric {
+    pub provider: String,
+    pub lyric: Lyric,
+    pub score: u32,
+}
+
+pub struct ProviderChain {
+    providers: Vec<Box<dyn LyricIface>>,
+    strategy: ChainStrategy,
+}
+
+impl ProviderChain {
+    pub fn new(strategy: ChainStrategy) -> Self {
+        ProviderChain {
+            providers: Vec::new(),
+            strategy,
+        }
+    }
+
+    /// The public providers in their historical order: LRCLib, then lyrics.ovh.
+    pub fn default_chain(options: &ProviderOptions) -> Result<Self, String> {
+        Self::from_names(&[LrcLibAPI::NAME, LyricApi::NAME], ChainStrategy::FirstSuccess, options)
+    }
+
+    /// Builds a chain from provider names, in the given order. The providers
+    /// share one HTTP client built from `options.http`.
+    pub fn from_names(names: &[&str], strategy: ChainStrategy, options: &ProviderOptions) -> Result<Self, String> {
+        let client = options.http.build().map_err(|e| e.to_string())?;
+        let mut chain = Self::new(strategy);
+        for name in names {
+            let provider = Self::public_provider(name, options, &client)
+                .ok_or_else(|| format!("Unknown lyrics provider: {}", name))?;
+            chain.push(provider);
+        }
+        Ok(chain)
+    }
+
+    /// Instantiates one of the providers shipped with this crate by name.
+    pub fn public_provider(name: &str, options: &ProviderOptions, client: &Client) -> Option<Box<dyn LyricIface>> {
+        let name = name.trim();
+        let base_url = options.base_url(name);
+        let throttle = Throttle::new(options.throttle(name));
+        match name {
+            LrcLibAPI::NAME => {
+                let mut api = if options.karaoke {
+                    LrcLibAPI::new_karaoke_lyrics()
+                } else {
+                    LrcLibAPI::new_plain_lyrics()
+                }
+                .with_client(client.clone())
+                .with_throttle(throttle);
+                if let Some(threshold) = options.search_threshold {
+                    api = api.with_search_threshold(threshold);
+                }
+                if let Some(tolerance) = options.duration_tolerance {
+                    api = api.with_duration_tolerance(tolerance);
+                }
+                Some(Box::new(match base_url {
+                    Some(url) => api.with_base_url(url),
+                    None => api,
+                }))
+            }
+            LyricApi::NAME => {
+                let api = LyricApi::new().with_client(client.clone()).with_throttle(throttle);
+                Some(Box::new(match base_url {
+                    Some(url) => api.with_base_url(url),
+                    None => api,
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Puts every provider added so far behind `cache`. `variant` keeps the
+    /// answers of differently configured chains apart, e.g. synced and plain.
+    pub fn with_cache(mut self, cache: Arc<LookupCache>, ttl: CacheTtl, variant: &str) -> Self {
+        self.providers = self
+            .providers
+            .into_iter()
+            .map(|provider| {
+                let cached = CachedProvider::new(provider, cache.clone()).with_ttl(ttl).with_variant(variant);
+                Box::new(cached) as Box<dyn LyricIface>
+            })
+            .collect();
+        self
+    }
+
+    pub fn with_provider(mut self, provider: Box<dyn LyricIface>) -> Self {
+        self.push(provider);
+        self
+    }
+
+    pub fn push(&mut self, provider: Box<dyn LyricIface>) {
+        self.providers.push(provider);
+    }
+
+    pub fn insert(&mut self, index: usize, provider: Box<dyn LyricIface>) {
+        let index = index.min(self.providers.len());
+        self.providers.insert(index, provider);
+    }
+
+    /// Removes every provider with the given name. Returns whether any was removed.
+    pub fn disable(&mut self, name: &str) -> bool {
+        let before = self.providers.len();
+        self.providers.retain(|provider| provider.name() != name);
+        before != self.providers.len()
+    }
+
+    /// Moves the named providers to the front, in the given order. Providers not
+    /// listed keep their relative order after them.
+    pub fn reorder(&mut self, names: &[&str]) {
+        self.providers.sort_by_key(|provider| {
+            names
+                .iter()
+                .position(|name| *name == provider.name())
+                .unwrap_or(names.len())
+        });
+    }
+
+    pub fn set_strategy(&mut self, strategy: ChainStrategy) {
+        self.strategy = strategy;
+    }
+
+    pub fn strategy(&self) -> ChainStrategy {
+        self.strategy
+    }
+
+    pub fn provider_names(&self) -> Vec<&str> {
+        self.providers.iter().map(|provider| provider.name()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+
+    /// When every provider fails, the most actionable error is returned: a
+    /// transient failure wins over "not found" so callers know a retry may help.
+    pub fn fetch(&self, song_metadata: &SongMetadata) -> Result<ProviderLyric, LyricError> {
+        let mut error = LyricError::NotFound;
+        let mut found = Vec::new();
+
+        for provider in &self.providers {
+            match provider.fetch_lyrics(song_metadata) {
+                Ok(lyric) => {
+                    let result = ProviderLyric {
+                        provider: provider.name().to_owned(),
+                        score: Self::score(&lyric),
+                        lyric,
+                    };
+                    debug!(
+                        "Provider {} answered for {} (score {})",
+                        result.provider, &song_metadata.title, result.score
+                    );
+                    if self.strategy == ChainStrategy::FirstSuccess {
+                        return Ok(result);
+                    }
+                    found.push(result);
+                }
+                Err(e) => {
+                    warn!("Provider {} failed for {}: {}", provider.name(), &song_metadata.title, e);
+                    if !e.falls_through() {
+                        return Err(e);
+                    }
+                    if error == LyricError::NotFound {
+                        error = e;
+                    }
+                }
+            }
+        }
+
+        let chosen = match self.strategy {
+            ChainStrategy::FirstSuccess => None,
+            ChainStrategy::BestScore => Self::best(found),
+            ChainStrategy::AllAndMerge => Self::merge(found),
+        };
+        match chosen {
+            Some(result) => {
+                info!("Using lyrics from {} for {}", result.provider, &song_metadata.title);
+                Ok(result)
+            }
+            None => Err(error),
+        }
+    }
+
+    fn best(found: Vec<ProviderLyric>) -> Option<ProviderLyric> {
+        // Keep the earliest provider on ties so chain order still matters.
+        found.into_iter().reduce(|best, candidate| {
+            if candidate.score > best.score { candidate } else { best }
+        })
+    }
+
+    fn merge(found: Vec<ProviderLyric>) -> Option<ProviderLyric> {
+        let mut found = found.into_iter();
+        let first = found.next()?;
+        if first.lyric.is_synced() {
+            return Some(first);
+        }
+        Some(found.find(|result| result.lyric.is_synced()).unwrap_or(first))
+    }
+
+    /// Synced lyrics always outrank plain ones; otherwise longer lyrics win.
+    pub fn score(lyric: &Lyric) -> u32 {
+        let lines = lyric
+            .lyric
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .count() as u32;
+        if lines == 0 {
+            return 0;
+        }
+        if lyric.is_synced() { 10_000 + lines } else { lines }
+    }
+}
+
+#[cfg(test)]
+mod test_provider_chain {
+    use super::*;
+    use crate::model::data_model::{FieldSources, Song};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct FakeProvider {
+        name: &'static str,
+        answer: Result<&'static str, LyricError>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl FakeProvider {
+        fn boxed(name: &'static str, answer: Option<&'static str>) -> Box<dyn LyricIface> {
+            Self::failing(name, answer.ok_or(LyricError::NotFound))
+        }
+
+        fn failing(name: &'static str, answer: Result<&'static str, LyricError>) -> Box<dyn LyricIface> {
+            Box::new(FakeProvider { name, answer, calls: Arc::new(AtomicUsize::new(0)) })
+        }
+    }
+
+    impl LyricIface for FakeProvider {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn fetch_lyrics(&self, song_metadata: &SongMetadata) -> Result<Lyric, LyricError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match &self.answer {
+                Ok(text) => Ok(Lyric { lyric: text.to_string(), song: song_metadata.song.clone() }),
+                Err(e) => Err(e.clone()),
+            }
+        }
+    }
+
+    fn metadata() -> SongMetadata {
+        SongMetadata {
+            song: Song::empty(),
+            artist: "Artist".to_owned(),
+            title: "Title".to_owned(),
+            album_title: String::new(),
+            duration: None,
+            sources: FieldSources::default(),
+        }
+    }
+
+    #[test]
+    fn first_success_stops_at_first_answer() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let chain = ProviderChain::new(ChainStrategy::FirstSuccess)
+            .with_provider(FakeProvider::boxed("missing", None))
+            .with_provider(FakeProvider::boxed("plain", Some("la la")))
+            .with_provider(Box::new(FakeProvider { name: "never", answer: Ok("x"), calls: calls.clone() }));
+
+        let result = chain.fetch(&metadata()).unwrap();
+        assert_eq!(result.provider, "plain");
+        assert_eq!(result.lyric.lyric, "la la");
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn best_score_prefers_synced_lyrics() {
+        let chain = ProviderChain::new(ChainStrategy::BestScore)
+            .with_provider(FakeProvider::boxed("plain", Some("one\ntwo\nthree")))
+            .with_provider(FakeProvider::boxed("synced", Some("[00:01.00] one\n[00:02.00] two")));
+
+        let result = chain.fetch(&metadata()).unwrap();
+        assert_eq!(result.provider, "synced");
+    }
+
+    #[test]
+    fn merge_keeps_first_unless_a_synced_lyric_exists() {
+        let chain = ProviderChain::new(ChainStrategy::AllAndMerge)
+            .with_provider(FakeProvider::boxed("short", Some("one")))
+            .with_provider(FakeProvider::boxed("long", Some("one\ntwo\nthree")));
+        assert_eq!(chain.fetch(&metadata()).unwrap().provider, "short");
+
+        let chain = chain.with_provider(FakeProvider::boxed("synced", Some("[00:01.00] one")));
+        assert_eq!(chain.fetch(&metadata()).unwrap().provider, "synced");
+    }
+
+    #[test]
+    fn disable_and_reorder_by_name() {
+        let mut chain = ProviderChain::new(ChainStrategy::FirstSuccess)
+            .with_provider(FakeProvider::boxed("a", Some("a")))
+            .with_provider(FakeProvider::boxed("b", Some("b")))
+            .with_provider(FakeProvider::boxed("c", Some("c")));
+
+        chain.reorder(&["c", "b"]);
+        assert_eq!(chain.provider_names(), vec!["c", "b", "a"]);
+        assert_eq!(chain.fetch(&metadata()).unwrap().provider, "c");
+
+        assert!(chain.disable("c"));
+        assert!(!chain.disable("c"));
+        assert_eq!(chain.fetch(&metadata()).unwrap().provider, "b");
+    }
+
+    #[test]
+    fn transient_failures_outrank_not_found() {
+        let chain = ProviderChain::new(ChainStrategy::FirstSuccess)
+            .with_provider(FakeProvider::boxed("a", None))
+            .with_provider(FakeProvider::failing("b", Err(LyricError::Upstream { status: 503 })))
+            .with_provider(FakeProvider::boxed("c", None));
+
+        assert_eq!(chain.fetch(&metadata()).unwrap_err(), LyricError::Upstream { status: 503 });
+        assert_eq!(
+            ProviderChain::new(ChainStrategy::BestScore).fetch(&metadata()).unwrap_err(),
+            LyricError::NotFound
+        );
+    }
+
+    #[test]
+    fn instrumental_stops_the_chain() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let chain = ProviderChain::new(ChainStrategy::BestScore)
+            .with_provider(FakeProvider::failing("a", Err(LyricError::Instrumental)))
+            .with_provider(Box::new(FakeProvider { name: "b", answer: Ok("x"), calls: calls.clone() }));
+
+        assert_eq!(chain.fetch(&metadata()).unwrap_err(), LyricError::Instrumental);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn public_providers_by_name() {
+        let options = ProviderOptions::new(true);
+        let chain = ProviderChain::default_chain(&options).unwrap();
+        assert_eq!(chain.provider_names(), vec![LrcLibAPI::NAME, LyricApi::NAME]);
+
+        let chain = ProviderChain::from_names(&["lyrics.ovh"], ChainStrategy::BestScore, &options).unwrap();
+        assert_eq!(chain.provider_names(), vec![LyricApi::NAME]);
+        assert!(ProviderChain::from_names(&["nope"], ChainStrategy::BestScore, &options).is_err());
+
+        let mut limited = ProviderOptions::new(false);
+        limited.set_throttle(LyricApi::NAME, ThrottleOptions::unlimited());
+        assert_eq!(limited.throttle(LyricApi::NAME), &ThrottleOptions::unlimited());
+        assert_eq!(limited.throttle(LrcLibAPI::NAME), &ThrottleOptions::default());
+
+        let mut proxied = ProviderOptions::new(false);
+        proxied.http = HttpOptions::default().with_proxy("not a url");
+        assert!(ProviderChain::default_chain(&proxied).is_err());
+        assert_eq!(ChainStrategy::from_name("best-score"), Some(ChainStrategy::BestScore));
+    }
+}
@@ -0,0 +1,223 @@
+use std::sync::LazyLock;
+
+use log::debug;
+use regex::Regex;
+
+use crate::api::lyric_error::LyricError;
+use crate::model::data_model::SongMetadata;
+
+/// `(feat. X)`, `[ft. X]`, `(with X)` anywhere in a title.
+static BRACKETED_FEATURING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\s*[(\[]\s*(feat\.?|ft\.?|featuring|with)\s[^)\]]*[)\]]").unwrap()
+});
+/// A trailing `feat. X` without brackets, in a title or artist.
+static TRAILING_FEATURING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\s+(feat\.?|ft\.?|featuring)\s.*$").unwrap());
+/// `(Live)`, `[2011 Remaster]`, `(Radio Edit)` and the like.
+static BRACKETED_VERSION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\s*[(\[][^)\]]*\b(remaster(ed)?|live|version|edit|mono|stereo|deluxe|bonus track)\b[^)\]]*[)\]]")
+        .unwrap()
+});
+/// `- Remastered 2011`, `- Live at Wembley`, `- Single Version` at the end of a title.
+static DASHED_VERSION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\s+[-–—]\s+[^-–—]*\b(remaster(ed)?|live|version|edit|mono|stereo|deluxe|bonus track)\b.*$")
+        .unwrap()
+});
+static BRACKETS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s*[(\[{][^)\]}]*[)\]}]").unwrap());
+/// Separators between several credited artists.
+static ARTIST_SEPARATOR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\s*(&|,|;|/|\band\b|\bx\b|\bvs\.?|\bwith\b)\s*").unwrap());
+static LEADING_ARTICLE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)^the\s+").unwrap());
+
+/// Relaxes song tags into query variants for providers that only find exact
+/// titles and artists.
+#[derive(Debug)]
+pub struct QueryNormalizer {}
+
+impl QueryNormalizer {
+    /// The tags as they are, followed by progressively relaxed copies:
+    /// without featuring credits, without remaster/live/version suffixes,
+    /// without any bracketed text, with the primary artist only and finally
+    /// without a leading "The". Duplicates and empty titles are dropped.
+    pub fn variants(song_metadata: &SongMetadata) -> Vec<SongMetadata> {
+        let mut title = Self::tidy(&song_metadata.title);
+        let mut artist = Self::tidy(&song_metadata.artist);
+        let mut steps = vec![(title.clone(), artist.clone())];
+
+        title = Self::strip_featuring(&title);
+        artist = Self::strip_featuring(&artist);
+        steps.push((title.clone(), artist.clone()));
+        title = Self::strip_version(&title);
+        steps.push((title.clone(), artist.clone()));
+        title = Self::strip_brackets(&title);
+        steps.push((title.clone(), artist.clone()));
+        artist = Self::primary_artist(&artist);
+        steps.push((title.clone(), artist.clone()));
+        artist = Self::strip_article(&artist);
+        steps.push((title, artist));
+
+        let mut variants: Vec<SongMetadata> = Vec::new();
+        for (title, artist) in steps {
+            let seen = variants.iter().any(|v| v.title == title && v.artist == artist);
+            if title.is_empty() || seen {
+                continue;
+            }
+            variants.push(SongMetadata { title, artist, ..song_metadata.clone() });
+        }
+        if variants.is_empty() {
+            variants.push(song_metadata.clone());
+        }
+        variants
+    }
+
+    /// Calls `fetch` with each variant in order until one is not a
+    /// [`LyricError::NotFound`].
+    pub fn first_match<T>(
+        variants: &[SongMetadata],
+        mut fetch: impl FnMut(&SongMetadata) -> Result<T, LyricError>,
+    ) -> Result<T, LyricError> {
+        for (i, variant) in variants.iter().enumerate() {
+            match fetch(variant) {
+                Err(LyricError::NotFound) => continue,
+                result => {
+                    if i > 0 && result.is_ok() {
+                        debug!("Matched relaxed query '{}' by '{}'", variant.title, variant.artist);
+                    }
+                    return result;
+                }
+            }
+        }
+        Err(LyricError::NotFound)
+    }
+
+    pub fn strip_featuring(text: &str) -> String {
+        let text = BRACKETED_FEATURING.replace_all(text, "");
+        Self::tidy(&TRAILING_FEATURING.replace(&text, ""))
+    }
+
+    pub fn strip_version(title: &str) -> String {
+        let title = BRACKETED_VERSION.replace_all(title, "");
+        Self::tidy(&DASHED_VERSION.replace(&title, ""))
+    }
+
+    pub fn strip_brackets(text: &str) -> String {
+        Self::tidy(&BRACKETS.replace_all(text, ""))
+    }
+
+    pub fn primary_artist(artist: &str) -> String {
+        let artist = Self::strip_featuring(artist);
+        let primary = ARTIST_SEPARATOR.split(&artist).find(|part| !part.trim().is_empty()).unwrap_or_default();
+        Self::tidy(primary)
+    }
+
+    pub fn strip_article(artist: &str) -> String {
+        let stripped = LEADING_ARTICLE.replace(artist, "");
+        if stripped.trim().is_empty() {
+            Self::tidy(artist)
+        } else {
+            Self::tidy(&stripped)
+        }
+    }
+
+    /// Collapses whitespace and drops dangling separators left by stripping.
+    fn tidy(text: &str) -> String {
+        text.split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+            .trim_end_matches([' ', '-', '–', '—', ',', ':'])
+            .to_owned()
+    }
+}
+
+#[cfg(test)]
+mod test_query_normalizer {
+    use super::*;
+    use crate::model::data_model::{FieldSources, Song};
+
+    fn metadata(artist: &str, title: &str) -> SongMetadata {
+        SongMetadata {
+            song: Song::empty(),
+            artist: artist.to_owned(),
+            title: title.to_owned(),
+            album_title: String::from("Album"),
+            duration: Some(200.0),
+            sources: FieldSources::default(),
+        }
+    }
+
+    fn queries(artist: &str, title: &str) -> Vec<(String, String)> {
+        QueryNormalizer::variants(&metadata(artist, title))
+            .into_iter()
+            .map(|v| (v.title, v.artist))
+            .collect()
+    }
+
+    #[test]
+    fn strips_featuring_credits() {
+        assert_eq!(QueryNormalizer::strip_featuring("Roses (feat. SAINt JHN)"), "Roses");
+        assert_eq!(QueryNormalizer::strip_featuring("Roses [ft. A & B] (Remix)"), "Roses (Remix)");
+        assert_eq!(QueryNormalizer::strip_featuring("Benny Blanco feat. Juice WRLD"), "Benny Blanco");
+        assert_eq!(QueryNormalizer::strip_featuring("Feather"), "Feather");
+    }
+
+    #[test]
+    fn strips_version_suffixes() {
+        assert_eq!(QueryNormalizer::strip_version("Song - Remastered 2011"), "Song");
+        assert_eq!(QueryNormalizer::strip_version("Song - 2011 Remaster"), "Song");
+        assert_eq!(QueryNormalizer::strip_version("Song [Live]"), "Song");
+        assert_eq!(QueryNormalizer::strip_version("Song (Radio Edit)"), "Song");
+        assert_eq!(QueryNormalizer::strip_version("Song - Single Version"), "Song");
+        assert_eq!(QueryNormalizer::strip_version("Live Forever"), "Live Forever");
+        assert_eq!(QueryNormalizer::strip_version("Song (Interlude)"), "Song (Interlude)");
+    }
+
+    #[test]
+    fn reduces_to_primary_artist() {
+        assert_eq!(QueryNormalizer::primary_artist("Artist & Other"), "Artist");
+        assert_eq!(QueryNormalizer::primary_artist("A, B and C"), "A");
+        assert_eq!(QueryNormalizer::primary_artist("A x B"), "A");
+        assert_eq!(QueryNormalizer::primary_artist("Solo"), "Solo");
+        assert_eq!(QueryNormalizer::strip_article("The Artist"), "Artist");
+        assert_eq!(QueryNormalizer::strip_article("The"), "The");
+    }
+
+    #[test]
+    fn variants_relax_progressively() {
+        assert_eq!(
+            queries("The Artist & Other", "Song (feat. X) - Remastered 2011 [Bonus]"),
+            vec![
+                (String::from("Song (feat. X) - Remastered 2011 [Bonus]"), String::from("The Artist & Other")),
+                (String::from("Song - Remastered 2011 [Bonus]"), String::from("The Artist & Other")),
+                (String::from("Song"), String::from("The Artist & Other")),
+                (String::from("Song"), String::from("The Artist")),
+                (String::from("Song"), String::from("Artist")),
+            ]
+        );
+    }
+
+    #[test]
+    fn clean_tags_have_a_single_variant() {
+        assert_eq!(queries("Benny Blanco", "Roses"), vec![(String::from("Roses"), String::from("Benny Blanco"))]);
+        let variant = &QueryNormalizer::variants(&metadata("A", "B"))[0];
+        assert_eq!(variant.album_title, "Album");
+        assert_eq!(variant.duration, Some(200.0));
+    }
+
+    #[test]
+    fn first_match_stops_at_anything_but_not_found() {
+        let variants = QueryNormalizer::variants(&metadata("A & B", "Song [Live]"));
+        let mut tried = Vec::new();
+        let found = QueryNormalizer::first_match(&variants, |v| {
+            tried.push(v.title.clone());
+            if v.title == "Song" { Ok(v.artist.clone()) } else { Err(LyricError::NotFound) }
+        });
+        assert_eq!(found, Ok(String::from("A & B")));
+        assert_eq!(tried, vec!["Song [Live]", "Song"]);
+
+        let failed: Result<(), LyricError> =
+            QueryNormalizer::first_match(&variants, |_| Err(LyricError::Network(String::from("down"))));
+        assert_eq!(failed, Err(LyricError::Network(String::from("down"))));
+        let missing: Result<(), LyricError> = QueryNormalizer::first_match(&variants, |_| Err(LyricError::NotFound));
+        assert_eq!(missing, Err(LyricError::NotFound));
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::debug;
+use reqwest::blocking::{RequestBuilder, Response};
+
+use crate::api::lyric_error::LyricError;
+
+/// Token bucket shared by every thread that uses a provider: up to `burst`
+/// requests go out at once, then `per_second` are allowed per second.
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    /// Available tokens and when they were last refilled.
+    state: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        RateLimiter {
+            per_second,
+            burst,
+            state: Mutex::new((burst, Instant::now())),
+        }
+    }
+
+    /// Takes a token if one is available, without waiting.
+    pub fn try_acquire(&self) -> bool {
+        self.take().is_none()
+    }
+
+    /// Blocks until a token is available and takes it.
+    pub fn acquire(&self) {
+        while let Some(wait) = self.take() {
+            thread::sleep(wait);
+        }
+    }
+
+    /// Takes a token, or returns how long until the next one.
+    fn take(&self) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap();
+        let (tokens, refilled) = &mut *state;
+        let now = Instant::now();
+        *tokens = (*tokens + now.duration_since(*refilled).as_secs_f64() * self.per_second).min(self.burst);
+        *refilled = now;
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - *tokens) / self.per_second))
+        }
+    }
+}
+
+/// Exponential backoff with jitter for transient failures.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying.
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for every further one.
+    pub base_delay: Duration,
+    /// Longest wait between two attempts. A `Retry-After` beyond it gives up.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// How long to wait before retry number `attempt` (0 based), or `None` to
+    /// give up. A server's `Retry-After` is used as is; otherwise the wait is
+    /// between half and all of `base_delay * 2^attempt`.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+        if let Some(retry_after) = retry_after {
+            return (retry_after <= self.max_delay).then_some(retry_after);
+        }
+        let backoff = self.base_delay.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_delay);
+        Some(backoff / 2 + backoff.mul_f64(Self::jitter() / 2.0))
+    }
+
+    /// A number in `[0, 1)`, different on every call.
+    fn jitter() -> f64 {
+        (RandomState::new().hash_one(Instant::now()) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Rate limit and retry settings of one provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThrottleOptions {
+    /// Requests per second; 0 means unlimited.
+    pub requests_per_second: f64,
+    pub burst: u32,
+    pub retry: RetryPolicy,
+}
+
+impl Default for ThrottleOptions {
+    fn default() -> Self {
+        ThrottleOptions {
+            requests_per_second: 5.0,
+            burst: 5,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+impl ThrottleOptions {
+    /// Sends every request right away and never retries.
+    pub fn unlimited() -> Self {
+        ThrottleOptions {
+            requests_per_second: 0.0,
+            burst: 1,
+            retry: RetryPolicy::none(),
+        }
+    }
+
+    /// Slowest rate accepted, one request every 1000 seconds. Anything lower
+    /// would wait longer than a [`Duration`] can hold.
+    pub const MIN_RATE: f64 = 0.001;
+
+    /// Checks a requests-per-second setting: 0 for unlimited, or at least
+    /// [`ThrottleOptions::MIN_RATE`].
+    pub fn check_rate(requests_per_second: f64) -> Result<f64, String> {
+        if requests_per_second == 0.0 || (requests_per_second.is_finite() && requests_per_second >= Self::MIN_RATE) {
+            Ok(requests_per_second)
+        } else {
+            Err(format!("rate limit must be 0 (unlimited) or at least {} requests per second", Self::MIN_RATE))
+        }
+    }
+
+    pub fn with_rate(mut self, requests_per_second: f64) -> Result<Self, String> {
+        self.requests_per_second = Self::check_rate(requests_per_second)?;
+        self.burst = (requests_per_second.ceil() as u32).max(1);
+        Ok(self)
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+/// Sends a provider's requests through its rate limiter and retries 429s,
+/// 5xx answers, timeouts and failed connections. Clones share the limiter.
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    limiter: Option<Arc<RateLimiter>>,
+    retry: RetryPolicy,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::new(&ThrottleOptions::unlimited())
+    }
+}
+
+impl Throttle {
+    pub fn new(options: &ThrottleOptions) -> Self {
+        Throttle {
+            limiter: (options.requests_per_second > 0.0)
+                .then(|| Arc::new(RateLimiter::new(options.requests_per_second, options.burst))),
+            retry: options.retry.clone(),
+        }
+    }
+
+    /// Sends `request` and returns the first response that is not worth
+    /// retrying, or the last one once the retries run out.
+    pub fn send(&self, request: RequestBuilder) -> Result<Response, LyricError> {
+        let mut attempt = 0;
+        loop {
+            let Some(next) = request.try_clone() else {
+                self.acquire();
+                return Ok(request.send()?);
+            };
+            self.acquire();
+            let (delay, reason) = match next.send() {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let error = LyricError::from_status(&response);
+                    let retry_after = match &error {
+                        LyricError::RateLimited { retry_after } => *retry_after,
+                        _ => None,
+                    };
+                    match self.retry.delay(attempt, retry_after).filter(|_| error.is_retryable()) {
+                        Some(delay) => (delay, error.to_string()),
+                        None => return Ok(response),
+                    }
+                }
+                Err(e) if e.is_timeout() || e.is_connect() => match self.retry.delay(attempt, None) {
+                    Some(delay) => (delay, e.to_string()),
+                    None => return Err(e.into()),
+                },
+                Err(e) => return Err(e.into()),
+            };
+            debug!("Retrying in {}ms after: {}", delay.as_millis(), reason);
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    fn acquire(&self) {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_throttle {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_a_burst_then_refills() {
+        let limiter = RateLimiter::new(20.0, 2);
+        assert!(limiter.try_acquire());
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+
+        let start = Instant::now();
+        limiter.acquire();
+        let waited = start.elapsed();
+        assert!(waited >= Duration::from_millis(40), "waited {:?}", waited);
+        assert!(waited < Duration::from_millis(500), "waited {:?}", waited);
+    }
+
+    #[test]
+    fn backoff_grows_with_jitter_and_stops() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+        };
+        for _ in 0..20 {
+            let first = policy.delay(0, None).unwrap();
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let second = policy.delay(1, None).unwrap();
+            assert!(second >= Duration::from_millis(100) && second <= Duration::from_millis(200));
+            let capped = policy.delay(2, None).unwrap();
+            assert!(capped >= Duration::from_millis(150) && capped <= Duration::from_millis(300));
+        }
+        assert_eq!(policy.delay(3, None), None);
+        assert_eq!(RetryPolicy::none().delay(0, None), None);
+    }
+
+    #[test]
+    fn retry_after_is_honored_up_to_the_cap() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(2))), Some(Duration::from_secs(2)));
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(3600))), None);
+    }
+
+    #[test]
+    fn rate_sets_the_burst() {
+        let options = ThrottleOptions::default().with_rate(2.5).unwrap();
+        assert_eq!((options.requests_per_second, options.burst), (2.5, 3));
+        assert_eq!(ThrottleOptions::default().with_rate(0.0).unwrap().requests_per_second, 0.0);
+        for rate in [1e-30, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(ThrottleOptions::default().with_rate(rate).is_err(), "{}", rate);
+        }
+        assert!(Throttle::new(&ThrottleOptions::unlimited()).limiter.is_none());
+        assert!(Throttle::new(&options).limiter.is_some());
+    }
+}
@@ -0,0 +1,445 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand};
+use clap_complete::Shell;
+use GetLyrics::api::lrclib_api::LrcLibAPI;
+use GetLyrics::api::lyric_api::LyricApi;
+use GetLyrics::api::throttle::ThrottleOptions;
+use GetLyrics::hasher::file_hash_helper::HashMode;
+use GetLyrics::metadata::path_pattern::PathPattern;
+use GetLyrics::model::data_model::{OverwritePolicy, ShiftMode, SubtitleFormat};
+
+/// Fetches lyrics for songs and writes them next to them as .lrc files.
+///
+/// Running without a subcommand is the same as `fetch`, so
+/// `getlyrics -k song.mp3` keeps working.
+#[derive(Debug, Parser)]
+#[command(name = "getlyrics", version, propagate_version = true)]
+pub struct Cli {
+    /// Config file to use instead of ~/.config/getlyrics/config.toml
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+impl Cli {
+    pub const SUBCOMMANDS: [&'static str; 10] =
+        ["fetch", "scan", "status", "shift", "convert", "cache", "db", "config", "completions", "help"];
+
+    /// Parses the arguments, reading a call without a subcommand as `fetch`.
+    pub fn parse_with_default() -> Self {
+        Self::parse_from(Self::with_default_subcommand(std::env::args_os().collect()))
+    }
+
+    pub fn with_default_subcommand(mut args: Vec<OsString>) -> Vec<OsString> {
+        // `--config` may come before the subcommand.
+        let mut index = 1;
+        while let Some(arg) = args.get(index).and_then(|arg| arg.to_str()) {
+            match arg {
+                "--config" => index += 2,
+                _ if arg.starts_with("--config=") => index += 1,
+                _ => break,
+            }
+        }
+        let explicit = match args.get(index).and_then(|arg| arg.to_str()) {
+            None => index == 1,
+            Some(arg) => Self::SUBCOMMANDS.contains(&arg) || ["-h", "--help", "-V", "--version"].contains(&arg),
+        };
+        if !explicit {
+            args.insert(index.min(args.len()), OsString::from("fetch"));
+        }
+        args
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Fetch lyrics for songs, or for the songs in folders
+    Fetch(FetchArgs),
+    /// Fetch lyrics for every song under folders, subfolders included
+    Scan(ScanArgs),
+    /// Show what earlier runs did with songs
+    Status(StatusArgs),
+    /// Move the timestamps of existing .lrc files
+    #[command(allow_negative_numbers = true)]
+    Shift(ShiftArgs),
+    /// Write subtitles from the .lrc files next to songs, without fetching anything
+    Convert(ConvertArgs),
+    /// Inspect or remove cached provider answers
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Inspect or edit the record of processed songs
+    #[command(subcommand)]
+    Db(DbCommand),
+    /// Inspect the configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Print a shell completion script
+    Completions {
+        shell: Shell,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct FetchArgs {
+    /// Include subfolders of the given folders
+    #[arg(short, long)]
+    pub recursive: bool,
+    #[command(flatten)]
+    pub options: FetchOptions,
+    /// Songs or folders of songs
+    #[arg(required = true, value_name = "FILE_OR_FOLDER")]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ScanArgs {
+    #[command(flatten)]
+    pub options: FetchOptions,
+    /// Music library folders
+    #[arg(required = true, value_name = "FOLDER")]
+    pub folders: Vec<PathBuf>,
+}
+
+/// Settings shared by `fetch` and `scan`.
+#[derive(Debug, Args)]
+pub struct FetchOptions {
+    /// Prefer synced lyrics
+    #[arg(short, long, help_heading = "Lyrics")]
+    pub karaoke: bool,
+    /// Add approximate word times to synced lyrics that have none
+    #[arg(long, help_heading = "Lyrics")]
+    pub word_timings: bool,
+    /// Fix out-of-order lines, duplicate timestamps and HTML in synced lyrics
+    #[arg(long, help_heading = "Lyrics")]
+    pub repair_lyrics: bool,
+    /// Seconds a track's length may differ from the lyric's
+    #[arg(long, value_name = "SECS", help_heading = "Lyrics")]
+    pub duration_tolerance: Option<f64>,
+    /// Layout used to read tags from paths, e.g. "{artist}/{album}/{track} - {title}"; repeatable
+    #[arg(long, value_name = "PATTERN", help_heading = "Lyrics")]
+    pub path_pattern: Vec<PathPattern>,
+
+    /// Write a .lrc file next to the song (default unless --embed is given)
+    #[arg(long, help_heading = "Output")]
+    pub sidecar: bool,
+    /// Store the lyrics in the song's tags
+    #[arg(long, help_heading = "Output")]
+    pub embed: bool,
+    /// Write the provider text without an ID tag header
+    #[arg(long, help_heading = "Output")]
+    pub no_header: bool,
+    /// Also write synced lyrics as subtitles
+    #[arg(long, value_name = "FORMATS", value_delimiter = ',', value_parser = subtitle_format, help_heading = "Output")]
+    pub subtitles: Vec<SubtitleFormat>,
+    /// Replace existing .lrc files: always, never, or upgrade plain ones to synced ones
+    #[arg(long, value_name = "POLICY", value_parser = overwrite_policy, help_heading = "Output")]
+    pub overwrite: Option<OverwritePolicy>,
+
+    /// LRCLib base URL, e.g. a self-hosted mirror
+    #[arg(long, value_name = "URL", help_heading = "Providers")]
+    pub lrclib_url: Option<String>,
+    /// lyrics.ovh base URL
+    #[arg(long, value_name = "URL", help_heading = "Providers")]
+    pub lyricsovh_url: Option<String>,
+    /// Minimum score of an LRCLib search result
+    #[arg(long, value_name = "0-1", value_parser = fraction, help_heading = "Providers")]
+    pub search_threshold: Option<f64>,
+    /// Requests per second, for every provider or as <provider>=<n>; repeatable
+    #[arg(long, value_name = "[PROVIDER=]N", value_parser = rate_limit, help_heading = "Providers")]
+    pub rate_limit: Vec<ProviderSetting<f64>>,
+    /// Retries of failed requests, for every provider or as <provider>=<n>; repeatable
+    #[arg(long, value_name = "[PROVIDER=]N", value_parser = ProviderSetting::<u32>::parse, help_heading = "Providers")]
+    pub retries: Vec<ProviderSetting<u32>>,
+
+    /// Proxy for every request, e.g. socks5://127.0.0.1:1080
+    #[arg(long, value_name = "URL", help_heading = "Network")]
+    pub proxy: Option<String>,
+    /// User-Agent sent to the providers
+    #[arg(long, value_name = "TEXT", help_heading = "Network")]
+    pub user_agent: Option<String>,
+    /// Seconds to wait for a connection
+    #[arg(long, value_name = "SECS", value_parser = seconds, help_heading = "Network")]
+    pub connect_timeout: Option<Duration>,
+    /// Seconds a whole request may take
+    #[arg(long, value_name = "SECS", value_parser = seconds, help_heading = "Network")]
+    pub timeout: Option<Duration>,
+
+    /// Songs processed at the same time
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..), help_heading = "Network")]
+    pub threads: Option<u16>,
+
+    /// Fetch songs again even if they were processed before
+    #[arg(short, long, help_heading = "State")]
+    pub force: bool,
+    /// Rehash every file instead of trusting unchanged size and modification time
+    #[arg(long, help_heading = "State")]
+    pub verify_hashes: bool,
+    /// What identifies a song: the whole file or only its audio
+    #[arg(long, value_name = "MODE", value_parser = hash_mode, help_heading = "State")]
+    pub hash_mode: Option<HashMode>,
+    /// Days before songs without lyrics are tried again
+    #[arg(long, value_name = "DAYS", value_parser = days, help_heading = "State")]
+    pub cooldown_days: Option<Duration>,
+
+    /// Ask the providers even if the answer is cached
+    #[arg(long, help_heading = "Cache")]
+    pub no_cache: bool,
+    #[command(flatten)]
+    pub cache_ttl: CacheTtlArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct CacheTtlArgs {
+    /// Days lyrics stay cached
+    #[arg(long, value_name = "DAYS", value_parser = days, help_heading = "Cache")]
+    pub cache_ttl: Option<Duration>,
+    /// Days "not found" answers stay cached
+    #[arg(long, value_name = "DAYS", value_parser = days, help_heading = "Cache")]
+    pub cache_miss_ttl: Option<Duration>,
+}
+
+#[derive(Debug, Args)]
+pub struct StatusArgs {
+    /// List every song instead of only counting them
+    #[arg(short, long)]
+    pub list: bool,
+    /// Only songs under these files or folders
+    #[arg(value_name = "FILE_OR_FOLDER")]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ShiftArgs {
+    /// Include subfolders of the given folders
+    #[arg(short, long)]
+    pub recursive: bool,
+    /// Move the timestamps themselves or record the shift in the [offset:] tag
+    #[arg(long, value_parser = shift_mode, default_value = "timestamps")]
+    pub mode: ShiftMode,
+    /// Milliseconds to move by; positive values make lyrics appear later
+    #[arg(value_name = "+/-MILLISECONDS")]
+    pub millis: i64,
+    /// .lrc files or folders of them
+    #[arg(required = true, value_name = "LRC_FILE_OR_FOLDER")]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ConvertArgs {
+    /// Include subfolders of the given folders
+    #[arg(short, long)]
+    pub recursive: bool,
+    /// Subtitle formats to write
+    #[arg(long, required = true, value_name = "FORMATS", value_delimiter = ',', value_parser = subtitle_format)]
+    pub subtitles: Vec<SubtitleFormat>,
+    /// Songs or folders of songs
+    #[arg(required = true, value_name = "FILE_OR_FOLDER")]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommand {
+    /// Count cached answers
+    Stats(CacheFilter),
+    /// List cached answers
+    List(CacheFilter),
+    /// Remove cached answers; without filters the whole cache
+    Purge(CacheFilter),
+}
+
+#[derive(Debug, Args)]
+pub struct CacheFilter {
+    /// Only answers of this provider
+    #[arg(long)]
+    pub provider: Option<String>,
+    /// Only "not found" answers
+    #[arg(long)]
+    pub misses: bool,
+    /// Only answers older than their TTL
+    #[arg(long)]
+    pub expired: bool,
+    #[command(flatten)]
+    pub cache_ttl: CacheTtlArgs,
+    /// Only answers whose artist or title contains this text
+    #[arg(value_name = "ARTIST_OR_TITLE")]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DbCommand {
+    /// Print where the database is
+    Path,
+    /// Print every record as a JSON line
+    Export,
+    /// Forget songs so the next run fetches them again
+    Forget {
+        /// Songs or folders of songs
+        #[arg(required = true, value_name = "FILE_OR_FOLDER")]
+        paths: Vec<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the configuration in effect, after the file and environment variables
+    Show,
+    /// Print which config file is read
+    Path,
+}
+
+/// A value for every provider, or for one as `<provider>=<value>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderSetting<T> {
+    pub provider: Option<String>,
+    pub value: T,
+}
+
+impl<T: FromStr> ProviderSetting<T> {
+    fn parse(text: &str) -> Result<Self, String> {
+        let (provider, value) = match text.rsplit_once('=') {
+            Some((provider, value)) => (Some(provider.trim().to_owned()), value),
+            None => (None, text),
+        };
+        if let Some(provider) = &provider
+            && ![LrcLibAPI::NAME, LyricApi::NAME].contains(&provider.as_str())
+        {
+            return Err(format!("unknown lyrics provider {}", provider));
+        }
+        let value = value.trim().parse().map_err(|_| format!("invalid value {}", value))?;
+        Ok(ProviderSetting { provider, value })
+    }
+}
+
+fn rate_limit(text: &str) -> Result<ProviderSetting<f64>, String> {
+    let setting = ProviderSetting::<f64>::parse(text)?;
+    ThrottleOptions::check_rate(setting.value)?;
+    Ok(setting)
+}
+
+fn subtitle_format(name: &str) -> Result<SubtitleFormat, String> {
+    SubtitleFormat::from_name(name).ok_or_else(|| format!("unknown subtitle format {}", name))
+}
+
+fn hash_mode(name: &str) -> Result<HashMode, String> {
+    HashMode::from_name(name).ok_or_else(|| format!("unknown hash mode {}", name))
+}
+
+fn overwrite_policy(name: &str) -> Result<OverwritePolicy, String> {
+    OverwritePolicy::from_name(name).ok_or_else(|| format!("unknown overwrite policy {}", name))
+}
+
+fn shift_mode(name: &str) -> Result<ShiftMode, String> {
+    ShiftMode::from_name(name).ok_or_else(|| format!("unknown shift mode {}", name))
+}
+
+fn fraction(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(value) if (0.0..=1.0).contains(&value) => Ok(value),
+        _ => Err(String::from("expected a number between 0 and 1")),
+    }
+}
+
+fn seconds(text: &str) -> Result<Duration, String> {
+    text.parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| String::from("expected a number of seconds"))
+}
+
+fn days(text: &str) -> Result<Duration, String> {
+    let days = text.parse::<u64>().map_err(|_| String::from("expected a number of days"))?;
+    days.checked_mul(86_400)
+        .map(Duration::from_secs)
+        .ok_or_else(|| String::from("number of days too large"))
+}
+
+#[cfg(test)]
+mod test_cli {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        let args = args.iter().map(OsString::from).collect();
+        Cli::try_parse_from(Cli::with_default_subcommand(args))
+    }
+
+    #[test]
+    fn definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn plain_invocations_fetch() {
+        let Command::Fetch(args) = parse(&["getlyrics", "-k", "a.mp3", "b.flac"]).unwrap().command else {
+            panic!("expected fetch");
+        };
+        assert!(args.options.karaoke);
+        assert_eq!(args.paths, vec![PathBuf::from("a.mp3"), PathBuf::from("b.flac")]);
+    }
+
+    #[test]
+    fn invalid_arguments_are_errors() {
+        assert!(parse(&["getlyrics", "--no-such-flag", "a.mp3"]).is_err());
+        assert!(parse(&["getlyrics", "fetch"]).is_err());
+        assert!(parse(&["getlyrics", "--search-threshold", "2", "a.mp3"]).is_err());
+        assert!(parse(&["getlyrics", "--rate-limit", "nope=2", "a.mp3"]).is_err());
+        assert!(parse(&["getlyrics", "--rate-limit", "1e-30", "a.mp3"]).is_err());
+        assert!(parse(&["getlyrics", "--rate-limit", "lrclib=NaN", "a.mp3"]).is_err());
+        assert!(parse(&["getlyrics", "--subtitles", "srt,doc", "a.mp3"]).is_err());
+        assert!(parse(&["getlyrics", "--cache-ttl", "18446744073709551615", "a.mp3"]).is_err());
+        assert!(parse(&["getlyrics", "--cooldown-days", "213503982334602", "a.mp3"]).is_err());
+    }
+
+    #[test]
+    fn provider_settings_and_lists() {
+        let Command::Scan(args) =
+            parse(&["getlyrics", "scan", "--rate-limit", "2", "--retries", "lrclib=5", "--subtitles", "srt,vtt", "/music"])
+                .unwrap()
+                .command
+        else {
+            panic!("expected scan");
+        };
+        assert_eq!(args.options.rate_limit, vec![ProviderSetting { provider: None, value: 2.0 }]);
+        assert_eq!(args.options.retries, vec![ProviderSetting { provider: Some(String::from("lrclib")), value: 5 }]);
+        assert_eq!(args.options.subtitles, vec![SubtitleFormat::Srt, SubtitleFormat::WebVtt]);
+    }
+
+    #[test]
+    fn config_comes_before_or_after_the_subcommand() {
+        let cli = parse(&["getlyrics", "--config", "my.toml", "--overwrite", "never", "a.mp3"]).unwrap();
+        assert_eq!(cli.config, Some(PathBuf::from("my.toml")));
+        let Command::Fetch(args) = cli.command else {
+            panic!("expected fetch");
+        };
+        assert_eq!(args.options.overwrite, Some(OverwritePolicy::Never));
+
+        let cli = parse(&["getlyrics", "--config=my.toml", "config", "show"]).unwrap();
+        assert!(matches!(cli.command, Command::Config(ConfigCommand::Show)));
+        assert_eq!(cli.config, Some(PathBuf::from("my.toml")));
+        let cli = parse(&["getlyrics", "status", "--config", "my.toml"]).unwrap();
+        assert_eq!(cli.config, Some(PathBuf::from("my.toml")));
+        assert!(parse(&["getlyrics", "--threads", "0", "a.mp3"]).is_err());
+    }
+
+    #[test]
+    fn shift_takes_negative_offsets() {
+        let Command::Shift(args) = parse(&["getlyrics", "shift", "-r", "-120", "/music"]).unwrap().command else {
+            panic!("expected shift");
+        };
+        assert_eq!(args.millis, -120);
+        assert!(args.recursive);
+        assert_eq!(parse_millis("+350"), 350);
+    }
+
+    fn parse_millis(millis: &str) -> i64 {
+        match parse(&["getlyrics", "shift", millis, "a.lrc"]).unwrap().command {
+            Command::Shift(args) => args.millis,
+            _ => panic!("expected shift"),
+        }
+    }
+}
@@ -0,0 +1,438 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use dirs::{config_dir, home_dir};
+use serde::{Deserialize, Serialize};
+
+use crate::api::http_client::HttpOptions;
+use crate::api::lrclib_api::LrcLibAPI;
+use crate::api::lyric_api::LyricApi;
+use crate::api::provider_chain::{ChainStrategy, ProviderOptions};
+use crate::api::throttle::ThrottleOptions;
+use crate::hasher::cooldown_policy::CooldownPolicy;
+use crate::hasher::file_hash_helper::HashMode;
+use crate::metadata::path_pattern::PathPattern;
+use crate::model::data_model::{OverwritePolicy, SubtitleFormat, WriterOptions};
+use crate::store::lookup_cache::CacheTtl;
+use crate::store::outcome_store::OutcomeStore;
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Settings read from `config.toml`. Every field is optional in the file;
+/// what is missing keeps the built-in default.
+///
+/// Precedence, lowest first: defaults, the file, environment variables
+/// ([`Config::apply_env`]), command line flags.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Songs processed at the same time.
+    pub threads: usize,
+    pub providers: ProvidersConfig,
+    pub http: HttpConfig,
+    pub output: OutputConfig,
+    pub lyrics: LyricsConfig,
+    pub state: StateConfig,
+    pub cache: CacheConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProvidersConfig {
+    /// Provider names, asked in this order.
+    pub order: Vec<String>,
+    /// `first`, `best` or `merge`, see [`ChainStrategy`].
+    pub strategy: String,
+    /// Prefer synced lyrics.
+    pub karaoke: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_threshold: Option<f64>,
+    /// Requests per second of every provider; 0 means unlimited.
+    pub rate_limit: f64,
+    pub retries: u32,
+    /// `[providers.<name>]` tables overriding the settings above.
+    #[serde(flatten)]
+    pub provider: BTreeMap<String, ProviderConfig>,
+}
+
+/// Settings of one provider.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProviderConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Seconds.
+    pub connect_timeout: f64,
+    /// Seconds.
+    pub timeout: f64,
+    pub user_agent: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    pub sidecar: bool,
+    pub embed: bool,
+    pub id_tags: bool,
+    /// `srt`, `vtt` or `ttml`.
+    pub subtitles: Vec<String>,
+    pub word_timings: bool,
+    /// `always`, `never` or `upgrade`, see [`OverwritePolicy`].
+    pub overwrite: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LyricsConfig {
+    pub repair: bool,
+    /// Seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_tolerance: Option<f64>,
+    /// Layouts used to read tags from paths; empty uses [`PathPattern::DEFAULTS`].
+    pub path_patterns: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StateConfig {
+    /// Folder of the state store and the lookup cache, `~/.getlyrics` if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    pub cooldown_days: u64,
+    /// `content` or `audio`, see [`HashMode`].
+    pub hash_mode: String,
+    pub verify_hashes: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub ttl_days: u64,
+    pub miss_ttl_days: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            threads: Self::DEFAULT_THREADS,
+            providers: ProvidersConfig::default(),
+            http: HttpConfig::default(),
+            output: OutputConfig::default(),
+            lyrics: LyricsConfig::default(),
+            state: StateConfig::default(),
+            cache: CacheConfig::default(),
+        }
+    }
+}
+
+impl Default for ProvidersConfig {
+    fn default() -> Self {
+        let throttle = ThrottleOptions::default();
+        ProvidersConfig {
+            order: vec![LrcLibAPI::NAME.to_owned(), LyricApi::NAME.to_owned()],
+            strategy: String::from("first"),
+            karaoke: false,
+            search_threshold: None,
+            rate_limit: throttle.requests_per_second,
+            retries: throttle.retry.max_retries,
+            provider: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        let http = HttpOptions::default();
+        HttpConfig {
+            connect_timeout: http.connect_timeout.as_secs_f64(),
+            timeout: http.timeout.as_secs_f64(),
+            user_agent: http.user_agent,
+            proxy: None,
+        }
+    }
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        let writer = WriterOptions::default();
+        OutputConfig {
+            sidecar: writer.sidecar,
+            embed: writer.embed,
+            id_tags: writer.id_tags,
+            subtitles: Vec::new(),
+            word_timings: writer.word_timings,
+            overwrite: writer.overwrite.name().to_owned(),
+        }
+    }
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        StateConfig {
+            dir: None,
+            cooldown_days: CooldownPolicy::DEFAULT_COOLDOWN.as_secs() / DAY,
+            hash_mode: String::from("content"),
+            verify_hashes: false,
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        let ttl = CacheTtl::default();
+        CacheConfig {
+            enabled: true,
+            ttl_days: ttl.hit.as_secs() / DAY,
+            miss_ttl_days: ttl.miss.as_secs() / DAY,
+        }
+    }
+}
+
+impl Config {
+    pub const FILENAME: &'static str = "config.toml";
+    /// Path of a config file to use instead of the default one.
+    pub const PATH_VARIABLE: &'static str = "GETLYRICS_CONFIG";
+    /// Limits API pressure.
+    pub const DEFAULT_THREADS: usize = 6;
+    pub const PROVIDERS: [&'static str; 2] = [LrcLibAPI::NAME, LyricApi::NAME];
+
+    /// `<config dir>/getlyrics/config.toml`, e.g. `~/.config/getlyrics/config.toml` on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("getlyrics").join(Self::FILENAME))
+    }
+
+    /// The file given with `--config`, else the one in `GETLYRICS_CONFIG`,
+    /// else the default one.
+    pub fn path(explicit: Option<&Path>) -> Option<PathBuf> {
+        explicit
+            .map(Path::to_path_buf)
+            .or_else(|| env::var_os(Self::PATH_VARIABLE).map(PathBuf::from))
+            .or_else(Self::default_path)
+    }
+
+    /// Reads the file [`Config::path`] selects, applies the environment
+    /// overrides and validates the result. Only a missing default file is
+    /// not an error.
+    pub fn load(explicit: Option<&Path>) -> Result<Config> {
+        let mut config = match Self::path(explicit) {
+            Some(path) if path.exists() || Some(&path) != Self::default_path().as_ref() => Self::from_file(&path)?,
+            _ => Config::default(),
+        };
+        config.apply_env(|name| env::var(name).ok())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read config {:?}", path))?;
+        Self::parse(&text).with_context(|| format!("Invalid config {:?}", path))
+    }
+
+    pub fn parse(text: &str) -> Result<Config> {
+        Ok(toml::from_str(text)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// Reads `GETLYRICS_PROVIDERS` (comma separated names), `GETLYRICS_STRATEGY`,
+    /// `GETLYRICS_LRCLIB_URL`, `GETLYRICS_LYRICSOVH_URL`, `GETLYRICS_THREADS`
+    /// and `GETLYRICS_STATE_DIR` through `var`.
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        if let Some(names) = var("GETLYRICS_PROVIDERS") {
+            self.providers.order = names
+                .split(',')
+                .map(|name| name.trim().to_owned())
+                .filter(|name| !name.is_empty())
+                .collect();
+        }
+        if let Some(strategy) = var("GETLYRICS_STRATEGY") {
+            self.providers.strategy = strategy;
+        }
+        for (name, variable) in [
+            (LrcLibAPI::NAME, "GETLYRICS_LRCLIB_URL"),
+            (LyricApi::NAME, "GETLYRICS_LYRICSOVH_URL"),
+        ] {
+            if let Some(url) = var(variable) {
+                self.providers.provider.entry(name.to_owned()).or_default().base_url = Some(url);
+            }
+        }
+        if let Some(threads) = var("GETLYRICS_THREADS") {
+            self.threads = threads
+                .trim()
+                .parse()
+                .with_context(|| format!("GETLYRICS_THREADS is not a number: {}", threads))?;
+        }
+        if let Some(dir) = var("GETLYRICS_STATE_DIR") {
+            self.state.dir = Some(PathBuf::from(dir));
+        }
+        Ok(())
+    }
+
+    /// Checks every setting that has to name or parse something.
+    pub fn validate(&self) -> Result<()> {
+        if self.threads == 0 {
+            bail!("threads must be at least 1");
+        }
+        if let Some(name) = self.providers.provider.keys().find(|name| !Self::PROVIDERS.contains(&name.as_str())) {
+            bail!("Unknown lyrics provider: {}", name);
+        }
+        if let Some(threshold) = self.providers.search_threshold
+            && !(0.0..=1.0).contains(&threshold)
+        {
+            bail!("search_threshold must be between 0 and 1");
+        }
+        for (days, name) in [
+            (self.state.cooldown_days, "cooldown_days"),
+            (self.cache.ttl_days, "ttl_days"),
+            (self.cache.miss_ttl_days, "miss_ttl_days"),
+        ] {
+            if days.checked_mul(DAY).is_none() {
+                bail!("{} is too large", name);
+            }
+        }
+        self.provider_names()?;
+        self.strategy()?;
+        self.provider_options()?;
+        self.writer_options()?;
+        self.hash_mode()?;
+        self.path_patterns()?;
+        Ok(())
+    }
+
+    pub fn provider_names(&self) -> Result<Vec<&str>> {
+        if self.providers.order.is_empty() {
+            bail!("No lyrics providers configured");
+        }
+        self.providers
+            .order
+            .iter()
+            .map(|name| match Self::PROVIDERS.contains(&name.as_str()) {
+                true => Ok(name.as_str()),
+                false => bail!("Unknown lyrics provider: {}", name),
+            })
+            .collect()
+    }
+
+    pub fn strategy(&self) -> Result<ChainStrategy> {
+        ChainStrategy::from_name(&self.providers.strategy)
+            .with_context(|| format!("Unknown provider strategy: {}", self.providers.strategy))
+    }
+
+    pub fn provider_options(&self) -> Result<ProviderOptions> {
+        let mut options = ProviderOptions::new(self.providers.karaoke);
+        options.search_threshold = self.providers.search_threshold;
+        options.duration_tolerance = self.lyrics.duration_tolerance;
+        options.http = HttpOptions::default()
+            .with_connect_timeout(Self::seconds(self.http.connect_timeout, "connect_timeout")?)
+            .with_timeout(Self::seconds(self.http.timeout, "timeout")?)
+            .with_user_agent(&self.http.user_agent);
+        if let Some(proxy) = &self.http.proxy {
+            options.http = options.http.with_proxy(proxy);
+        }
+        options.throttle = Self::throttle(ThrottleOptions::default(), Some(self.providers.rate_limit), Some(self.providers.retries))?;
+        for (name, provider) in &self.providers.provider {
+            if let Some(url) = &provider.base_url {
+                options.set_base_url(name, url);
+            }
+            if provider.rate_limit.is_some() || provider.retries.is_some() {
+                let throttle = Self::throttle(options.throttle.clone(), provider.rate_limit, provider.retries)?;
+                options.set_throttle(name, throttle);
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn writer_options(&self) -> Result<WriterOptions> {
+        let subtitles = self
+            .output
+            .subtitles
+            .iter()
+            .map(|name| SubtitleFormat::from_name(name).with_context(|| format!("Unknown subtitle format: {}", name)))
+            .collect::<Result<Vec<SubtitleFormat>>>()?;
+        let overwrite = OverwritePolicy::from_name(&self.output.overwrite)
+            .with_context(|| format!("Unknown overwrite policy: {}", self.output.overwrite))?;
+        Ok(WriterOptions {
+            sidecar: self.output.sidecar,
+            embed: self.output.embed,
+            id_tags: self.output.id_tags,
+            subtitles,
+            word_timings: self.output.word_timings,
+            overwrite,
+        })
+    }
+
+    pub fn hash_mode(&self) -> Result<HashMode> {
+        HashMode::from_name(&self.state.hash_mode).with_context(|| format!("Unknown hash mode: {}", self.state.hash_mode))
+    }
+
+    pub fn cooldown(&self) -> Duration {
+        Self::days(self.state.cooldown_days)
+    }
+
+    pub fn cache_ttl(&self) -> CacheTtl {
+        CacheTtl {
+            hit: Self::days(self.cache.ttl_days),
+            miss: Self::days(self.cache.miss_ttl_days),
+        }
+    }
+
+    /// The configured patterns, or the built-in ones when there are none.
+    pub fn path_patterns(&self) -> Result<Vec<PathPattern>> {
+        if self.lyrics.path_patterns.is_empty() {
+            return Ok(PathPattern::defaults());
+        }
+        self.lyrics
+            .path_patterns
+            .iter()
+            .map(|pattern| PathPattern::parse(pattern).map_err(anyhow::Error::msg))
+            .collect()
+    }
+
+    /// The configured state folder with a leading `~` expanded, or `~/.getlyrics`.
+    pub fn state_dir(&self) -> Result<PathBuf> {
+        match &self.state.dir {
+            Some(dir) => match dir.strip_prefix("~") {
+                Ok(rest) => Ok(home_dir().context("Could not resolve home directory")?.join(rest)),
+                Err(_) => Ok(dir.clone()),
+            },
+            None => OutcomeStore::default_dir(),
+        }
+    }
+
+    /// [`Config::validate`] rejects day counts this would have to saturate.
+    fn days(days: u64) -> Duration {
+        Duration::from_secs(days.saturating_mul(DAY))
+    }
+
+    fn seconds(seconds: f64, name: &str) -> Result<Duration> {
+        Duration::try_from_secs_f64(seconds).with_context(|| format!("{} must be a number of seconds", name))
+    }
+
+    fn throttle(base: ThrottleOptions, rate_limit: Option<f64>, retries: Option<u32>) -> Result<ThrottleOptions> {
+        let mut throttle = match rate_limit {
+            Some(rate) => base.with_rate(rate).map_err(anyhow::Error::msg)?,
+            None => base,
+        };
+        if let Some(retries) = retries {
+            throttle.retry = throttle.retry.with_max_retries(retries);
+        }
+        Ok(throttle)
+    }
+}
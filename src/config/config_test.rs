@@ -0,0 +1,132 @@
+#[cfg(test)]
+mod config_tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use crate::api::provider_chain::ChainStrategy;
+    use crate::config::config::Config;
+    use crate::model::data_model::{OverwritePolicy, SubtitleFormat};
+
+    const FILE: &str = r#"
+threads = 2
+
+[providers]
+order = ["lyrics.ovh", "lrclib"]
+strategy = "best"
+karaoke = true
+rate_limit = 2.0
+
+[providers.lrclib]
+base_url = "http://mirror.local/api"
+retries = 0
+
+[output]
+subtitles = ["srt", "vtt"]
+overwrite = "upgrade"
+
+[lyrics]
+path_patterns = ["{artist} - {title}"]
+
+[state]
+dir = "/srv/getlyrics"
+hash_mode = "audio"
+"#;
+
+    #[test]
+    fn test_defaults_match_the_built_in_behaviour() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config, Config::default());
+        config.validate().unwrap();
+        assert_eq!(config.threads, Config::DEFAULT_THREADS);
+        assert_eq!(config.provider_names().unwrap(), vec!["lrclib", "lyrics.ovh"]);
+        assert_eq!(config.strategy().unwrap(), ChainStrategy::FirstSuccess);
+        assert_eq!(config.writer_options().unwrap(), Default::default());
+        assert_eq!(config.cache_ttl(), Default::default());
+        assert_eq!(config.path_patterns().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_file_settings() {
+        let config = Config::parse(FILE).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.threads, 2);
+        assert_eq!(config.provider_names().unwrap(), vec!["lyrics.ovh", "lrclib"]);
+        assert_eq!(config.strategy().unwrap(), ChainStrategy::BestScore);
+
+        let options = config.provider_options().unwrap();
+        assert!(options.karaoke);
+        assert_eq!(options.base_url("lrclib"), Some("http://mirror.local/api"));
+        assert_eq!(options.throttle("lrclib").requests_per_second, 2.0);
+        assert_eq!(options.throttle("lrclib").retry.max_retries, 0);
+        assert_eq!(options.throttle("lyrics.ovh").retry.max_retries, 3);
+
+        let writer = config.writer_options().unwrap();
+        assert_eq!(writer.subtitles, vec![SubtitleFormat::Srt, SubtitleFormat::WebVtt]);
+        assert_eq!(writer.overwrite, OverwritePolicy::Upgrade);
+        assert!(writer.sidecar);
+        assert_eq!(config.path_patterns().unwrap()[0].pattern(), "{artist} - {title}");
+        assert_eq!(config.state_dir().unwrap(), PathBuf::from("/srv/getlyrics"));
+    }
+
+    #[test]
+    fn test_environment_overrides_the_file() {
+        let variables = HashMap::from([
+            ("GETLYRICS_PROVIDERS", "lrclib"),
+            ("GETLYRICS_STRATEGY", "merge"),
+            ("GETLYRICS_LYRICSOVH_URL", "http://ovh.local"),
+            ("GETLYRICS_THREADS", "3"),
+        ]);
+        let mut config = Config::parse(FILE).unwrap();
+        config.apply_env(|name| variables.get(name).map(|value| value.to_string())).unwrap();
+        assert_eq!(config.provider_names().unwrap(), vec!["lrclib"]);
+        assert_eq!(config.strategy().unwrap(), ChainStrategy::AllAndMerge);
+        assert_eq!(config.provider_options().unwrap().base_url("lyrics.ovh"), Some("http://ovh.local"));
+        assert_eq!(config.provider_options().unwrap().base_url("lrclib"), Some("http://mirror.local/api"));
+        assert_eq!(config.threads, 3);
+
+        assert!(config.apply_env(|_| Some(String::from("many"))).is_err());
+    }
+
+    #[test]
+    fn test_invalid_settings_are_errors() {
+        assert!(Config::parse("thread = 2").is_err());
+        assert!(Config::parse("[output]\noverwrite = true").is_err());
+        for file in [
+            "threads = 0",
+            "[providers]\norder = [\"genius\"]",
+            "[providers]\nstrategy = \"fastest\"",
+            "[providers.genius]\nretries = 1",
+            "[output]\nsubtitles = [\"doc\"]",
+            "[output]\noverwrite = \"sometimes\"",
+            "[state]\nhash_mode = \"tags\"",
+            "[lyrics]\npath_patterns = [\"{artist\"]",
+            "[http]\ntimeout = -1.0",
+            "[state]\ncooldown_days = 213503982334602",
+            "[cache]\nmiss_ttl_days = 213503982334602",
+            "[providers]\nrate_limit = 1e-30",
+            "[providers]\nrate_limit = nan",
+            "[providers.lrclib]\nrate_limit = -1.0",
+        ] {
+            assert!(Config::parse(file).unwrap().validate().is_err(), "{}", file);
+        }
+    }
+
+    #[test]
+    fn test_show_round_trips() {
+        let config = Config::parse(FILE).unwrap();
+        let shown = config.to_toml().unwrap();
+        assert!(shown.contains("[providers.lrclib]"));
+        assert_eq!(Config::parse(&shown).unwrap(), config);
+        assert_eq!(Config::default().cooldown(), Duration::from_secs(7 * 24 * 60 * 60));
+    }
+
+    #[test]
+    fn test_load_reads_an_explicit_file() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join(Config::FILENAME);
+        std::fs::write(&path, "[cache]\nenabled = false\n").unwrap();
+        assert!(!Config::load(Some(&path)).unwrap().cache.enabled);
+        assert!(Config::load(Some(&tmp_dir.path().join("missing.toml"))).is_err());
+    }
+}
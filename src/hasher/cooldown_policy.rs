@@ -0,0 +1,173 @@
+use crate::hasher::file_hash_helper::FileHashHelper;
+use crate::store::outcome_store::{now, ProcessRecord};
+use crate::traits::traits::ProcessPolicy;
+use anyhow::Result;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Skips tracks with a final outcome, and retries "not found" and "error"
+/// tracks once their cooldown has elapsed. The cooldown doubles with every
+/// consecutive unsuccessful attempt, up to `max_cooldown`.
+pub struct CooldownPolicy {
+    hashes: FileHashHelper,
+    cooldown: Duration,
+    max_cooldown: Duration,
+}
+
+impl CooldownPolicy {
+    pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+    pub const DEFAULT_MAX_COOLDOWN: Duration = Duration::from_secs(180 * 24 * 60 * 60);
+
+    pub fn new(cooldown: Duration) -> Result<Self> {
+        Ok(Self::with_helper(FileHashHelper::new()?, cooldown))
+    }
+
+    pub fn new_with_trait(cooldown: Duration) -> Result<Arc<dyn ProcessPolicy>> {
+        Ok(Arc::new(Self::new(cooldown)?))
+    }
+
+    pub fn with_helper(hashes: FileHashHelper, cooldown: Duration) -> Self {
+        Self {
+            hashes,
+            cooldown,
+            max_cooldown: Self::DEFAULT_MAX_COOLDOWN.max(cooldown),
+        }
+    }
+
+    pub fn with_max_cooldown(mut self, max_cooldown: Duration) -> Self {
+        self.max_cooldown = max_cooldown;
+        self
+    }
+
+    pub fn hashes(&self) -> &FileHashHelper {
+        &self.hashes
+    }
+
+    /// `cooldown * 2^(attempts - 1)`, capped at `max_cooldown`.
+    pub fn cooldown_for(&self, attempts: u32) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(31);
+        self.cooldown
+            .checked_mul(1 << exponent)
+            .unwrap_or(self.max_cooldown)
+            .min(self.max_cooldown)
+    }
+
+    /// Whether a file with this last record should be looked up again at `now`.
+    pub fn is_due(&self, record: Option<&ProcessRecord>, now: u64) -> bool {
+        match record {
+            None => true,
+            Some(record) if record.outcome.is_final() => false,
+            Some(record) => {
+                let retry_at = record.timestamp.saturating_add(self.cooldown_for(record.attempts).as_secs());
+                now >= retry_at
+            }
+        }
+    }
+}
+
+impl ProcessPolicy for CooldownPolicy {
+    fn should_process(&self, path: &Path) -> Result<bool> {
+        let now = now();
+        self.hashes.should_process_with(path, |record| self.is_due(record, now))
+    }
+
+    fn record(&self, record: &ProcessRecord) -> Result<()> {
+        self.hashes.record(record)
+    }
+}
+
+#[cfg(test)]
+mod test_cooldown_policy {
+    use super::*;
+    use crate::store::outcome_store::{Outcome, OutcomeStore};
+    use std::path::PathBuf;
+
+    const DEFAULT_SONG_NAME: &str = "test_resources/benny_blanco-roses.mp3";
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn make_policy(tmp_dir: &Path) -> Result<CooldownPolicy> {
+        let store = OutcomeStore::open(&tmp_dir.join(OutcomeStore::FILENAME))?;
+        Ok(CooldownPolicy::with_helper(FileHashHelper::with_store(store), Duration::from_secs(7 * DAY)))
+    }
+
+    fn record(outcome: Outcome, days_ago: u64, attempts: u32) -> ProcessRecord {
+        let mut record = ProcessRecord::new(Path::new(DEFAULT_SONG_NAME), outcome);
+        record.timestamp = now() - days_ago * DAY;
+        record.attempts = attempts;
+        record
+    }
+
+    #[test]
+    fn cooldown_doubles_per_attempt() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let policy = make_policy(tmp_dir.path())?.with_max_cooldown(Duration::from_secs(30 * DAY));
+
+        assert_eq!(policy.cooldown_for(0), Duration::from_secs(7 * DAY));
+        assert_eq!(policy.cooldown_for(1), Duration::from_secs(7 * DAY));
+        assert_eq!(policy.cooldown_for(2), Duration::from_secs(14 * DAY));
+        assert_eq!(policy.cooldown_for(3), Duration::from_secs(28 * DAY));
+        assert_eq!(policy.cooldown_for(4), Duration::from_secs(30 * DAY));
+        assert_eq!(policy.cooldown_for(u32::MAX), Duration::from_secs(30 * DAY));
+        Ok(())
+    }
+
+    #[test]
+    fn final_outcomes_are_never_retried() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let policy = make_policy(tmp_dir.path())?;
+        let now = now();
+
+        assert!(policy.is_due(None, now));
+        assert!(!policy.is_due(Some(&record(Outcome::Written, 1000, 0)), now));
+        assert!(!policy.is_due(Some(&record(Outcome::Instrumental, 1000, 0)), now));
+        Ok(())
+    }
+
+    #[test]
+    fn failures_are_retried_after_backoff() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let policy = make_policy(tmp_dir.path())?;
+        let now = now();
+
+        assert!(!policy.is_due(Some(&record(Outcome::NotFound, 6, 1)), now));
+        assert!(policy.is_due(Some(&record(Outcome::NotFound, 7, 1)), now));
+        assert!(policy.is_due(Some(&record(Outcome::Error, 8, 1)), now));
+        assert!(!policy.is_due(Some(&record(Outcome::NotFound, 13, 2)), now));
+        assert!(policy.is_due(Some(&record(Outcome::NotFound, 14, 2)), now));
+        Ok(())
+    }
+
+    #[test]
+    fn should_process_uses_stored_outcome() -> Result<()> {
+        let mp3_path = PathBuf::from(DEFAULT_SONG_NAME);
+        let tmp_dir = tempfile::tempdir()?;
+        let policy = make_policy(tmp_dir.path())?;
+
+        assert!(policy.should_process(&mp3_path)?);
+        policy.record(&ProcessRecord::new(&mp3_path, Outcome::NotFound))?;
+        assert!(!policy.should_process(&mp3_path)?);
+
+        let stored = policy.hashes.store().all()?;
+        assert_eq!(stored.len(), 1);
+        let (hash, _) = &stored[0];
+        policy.hashes.store().put(hash, &record(Outcome::NotFound, 7, 1))?;
+        assert!(policy.should_process(&mp3_path)?);
+        Ok(())
+    }
+
+    #[test]
+    fn legacy_hashes_are_retried_after_the_cooldown() -> Result<()> {
+        let mp3_path = PathBuf::from(DEFAULT_SONG_NAME);
+        let tmp_dir = tempfile::tempdir()?;
+        let policy = make_policy(tmp_dir.path())?;
+        let hash = policy.hashes.hash_file(&mp3_path)?;
+
+        policy.hashes.store().import_legacy_hashes(std::slice::from_ref(&hash))?;
+        let imported = policy.hashes.store().get(&hash)?.unwrap();
+        assert!(!imported.outcome.is_final());
+        assert!(!policy.should_process(&mp3_path)?);
+        assert!(policy.is_due(Some(&imported), imported.timestamp + 7 * DAY));
+        Ok(())
+    }
+}
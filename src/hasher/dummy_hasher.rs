@@ -4,7 +4,7 @@ use std::sync::Arc;
 
 pub struct DummyHasher;
 impl DummyHasher {
-    pub fn new() -> Arc<dyn ProcessPolicy> {
+    pub fn new() -> Arc<Self> {
         Arc::new(DummyHasher)
     }
 }
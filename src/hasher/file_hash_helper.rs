@@ -1,65 +1,154 @@
+use crate::store::outcome_store::{Outcome, OutcomeStore, ProcessRecord};
 use crate::traits::traits::ProcessPolicy;
 use anyhow::{Context, Result};
-use dirs::home_dir;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use symphonia::core::formats::{FormatOptions, TrackType};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::default::get_probe;
 
-use std::collections::HashSet;
-use std::fs::{File, OpenOptions};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
 use std::io::{BufReader, Read};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::{
     fs,
-    io::{BufRead, Write},
+    io::BufRead,
     path::Path,
 };
 
+/// What [`FileHashHelper::hash_file`] hashes.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashMode {
+    /// Every byte of the file.
+    #[default]
+    Content,
+    /// Only the audio packets of the default track, so tag edits keep the hash
+    /// and the same stream in another container shares it. Files that can't
+    /// be demuxed fall back to [`HashMode::Content`].
+    Audio,
+}
+
+impl HashMode {
+    pub fn from_name(name: &str) -> Option<HashMode> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content" | "file" => Some(HashMode::Content),
+            "audio" => Some(HashMode::Audio),
+            _ => None,
+        }
+    }
+}
+
 pub struct FileHashHelper {
-    hashes: Mutex<HashSet<String>>,
-    store_file: Mutex<File>, // adding a mutex to handle rayon parallelism
+    store: OutcomeStore,
+    mode: HashMode,
+    hashes: Mutex<HashMap<PathBuf, String>>, // hashes computed during this run, reused by `record`
+    in_flight: Mutex<HashSet<String>>,       // contents claimed by a rayon worker during this run
 }
 
 impl FileHashHelper {
 
     const FILENAME: &'static str = "processed_hashes.txt";
     pub fn new() -> Result<Self> {
-        let mut dir = home_dir().context("Could not resolve home directory")?;
-        dir.push(".getlyrics");
+        Self::in_dir(&OutcomeStore::default_dir()?)
+    }
 
+    /// Opens the state store in `dir`, migrating a legacy hash list found there.
+    pub fn in_dir(dir: &Path) -> Result<Self> {
         if !dir.exists() {
-            fs::create_dir_all(&dir).context("Failed to create ~/.getlyrics directory")?;
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
         }
 
-        let mut store_path = dir.clone();
-        store_path.push(FileHashHelper::FILENAME);
+        let store = OutcomeStore::open(&dir.join(OutcomeStore::FILENAME))?;
 
-        if !store_path.exists() {
-            fs::File::create(&store_path).context("Failed to create hash store file")?;
+        let legacy_path = dir.join(FileHashHelper::FILENAME);
+        if legacy_path.exists() {
+            Self::migrate_legacy_store(&store, &legacy_path)?;
         }
-        let file = File::open(&store_path)?;
-        let reader = BufReader::new(file);
-        let mut set = HashSet::new();
 
-        for line in reader.lines().flatten() {
-            set.insert(line);
+        Ok(Self::with_store(store))
+    }
+
+    pub fn new_with_trait() -> Result<Arc<dyn ProcessPolicy>> {
+        Ok(Arc::new(Self::new()?))
+    }
+
+    pub fn with_store(store: OutcomeStore) -> Self {
+        Self {
+            store,
+            mode: HashMode::Content,
+            hashes: Mutex::new(HashMap::new()),
+            in_flight: Mutex::new(HashSet::new()),
         }
+    }
 
-        // 🔹 Open file in append mode for future writes
-        let store_file = OpenOptions::new()
-            .append(true)
-            .open(&store_path)?;
+    pub fn with_mode(mut self, mode: HashMode) -> Self {
+        self.mode = mode;
+        self
+    }
 
-        Ok(Self {
-            hashes: Mutex::new(set),
-            store_file: Mutex::new(store_file),
-        })
+    pub fn mode(&self) -> HashMode {
+        self.mode
     }
 
-    pub fn new_with_trait() -> Result<Arc<dyn ProcessPolicy>> {
-        Ok(Arc::new(Self::new()?))
+    /// Moves the hashes of `processed_hashes.txt` into the state store and
+    /// renames the old file so the import only happens once.
+    fn migrate_legacy_store(store: &OutcomeStore, legacy_path: &Path) -> Result<()> {
+        let reader = BufReader::new(File::open(legacy_path)?);
+        let hashes: Vec<String> = reader
+            .lines()
+            .map_while(Result::ok)
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        let imported = store.import_legacy_hashes(&hashes)?;
+        fs::rename(legacy_path, legacy_path.with_extension("txt.migrated"))
+            .context("Failed to rename legacy hash store")?;
+        info!("Imported {} hashes from {}", imported, legacy_path.display());
+        Ok(())
+    }
+
+    /// SHA-256 of the file according to the hash mode.
+    pub fn hash_file(&self, path: &Path) -> Result<String> {
+        if self.mode == HashMode::Audio {
+            match Self::hash_audio(path) {
+                Ok(hash) => return Ok(hash),
+                Err(e) => debug!("Hashing whole file {}, audio is unreadable: {}", path.display(), e),
+            }
+        }
+        Self::hash_content(path)
+    }
+
+    /// SHA-256 of the audio packets of the default track, prefixed with `audio:`
+    /// so it never collides with a content hash.
+    pub fn hash_audio(path: &Path) -> Result<String> {
+        let file = File::open(path).with_context(|| format!("Failed to open file: {:?}", path))?;
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut reader = get_probe().probe(
+            &Default::default(),
+            mss,
+            FormatOptions::default(),
+            MetadataOptions::default(),
+        )?;
+        let track_id = reader
+            .default_track(TrackType::Audio)
+            .context("No audio track found")?
+            .id;
+
+        let mut hasher = Sha256::new();
+        while let Some(packet) = reader.next_packet()? {
+            if packet.track_id == track_id {
+                hasher.update(&packet.data);
+            }
+        }
+        Ok(format!("audio:{}", hex::encode(hasher.finalize())))
     }
 
     /// Compute SHA-256 of any file.
-    fn hash_file(&self, path: &Path) -> Result<String> {
+    pub fn hash_content(path: &Path) -> Result<String> {
         let mut file =
             fs::File::open(path).with_context(|| format!("Failed to open file: {:?}", path))?;
 
@@ -76,24 +165,82 @@ impl FileHashHelper {
 
         Ok(hash)
     }
-}
 
-impl ProcessPolicy for FileHashHelper {
-    /// High-level helper: check + optionally store.
-    fn should_process(&self, path: &Path) -> Result<bool> {
+    /// Errors are transient, so they are retried on the next run. Every other
+    /// outcome is an answer that refetching would not change.
+    fn needs_processing(record: Option<&ProcessRecord>) -> bool {
+        match record {
+            None => true,
+            Some(record) => record.outcome == Outcome::Error,
+        }
+    }
+
+    /// Looks up the last outcome of the file's content and lets `decide` choose.
+    /// Accepted files are claimed so parallel workers don't process duplicates.
+    pub fn should_process_with(
+        &self,
+        path: &Path,
+        decide: impl Fn(Option<&ProcessRecord>) -> bool,
+    ) -> Result<bool> {
         let hash = self.hash_file(path)?;
-        let mut set = self.hashes.lock().unwrap();
-        if set.contains(&hash) {
+        self.should_process_hash(path, hash, decide)
+    }
+
+    /// Like [`Self::should_process_with`], for callers that already know the content hash.
+    pub fn should_process_hash(
+        &self,
+        path: &Path,
+        hash: String,
+        decide: impl Fn(Option<&ProcessRecord>) -> bool,
+    ) -> Result<bool> {
+        if !decide(self.store.get(&hash)?.as_ref()) {
+            return Ok(false);
+        }
+        if !self.in_flight.lock().unwrap().insert(hash.clone()) {
             return Ok(false);
         }
-        set.insert(hash.clone());
-        drop(set); // release lock ASAP
+        self.hashes.lock().unwrap().insert(path.to_owned(), hash);
+        Ok(true)
+    }
 
-        // Append to file (separate lock)
-        let mut file = self.store_file.lock().unwrap();
-        writeln!(file, "{}", hash)?;
+    pub fn store(&self) -> &OutcomeStore {
+        &self.store
+    }
 
-        Ok(true)
+    /// [`ProcessPolicy::record`], returning the hash the record was stored
+    /// under. A file with embedded lyrics is hashed again, since the hash
+    /// taken by `should_process` was of the file before the lyrics were added.
+    pub fn store_record(&self, record: &ProcessRecord) -> Result<String> {
+        let cached = self.hashes.lock().unwrap().remove(&record.path);
+        let hash = match cached {
+            Some(hash) if !record.embedded => hash,
+            Some(previous) => {
+                self.in_flight.lock().unwrap().remove(&previous);
+                self.hash_file(&record.path)?
+            }
+            None => self.hash_file(&record.path)?,
+        };
+        let mut record = record.clone();
+        record.attempts = match self.store.get(&hash)? {
+            _ if record.outcome.is_final() => 0,
+            Some(previous) if !previous.outcome.is_final() => previous.attempts + 1,
+            _ => 1,
+        };
+        self.store.put(&hash, &record)?;
+        self.in_flight.lock().unwrap().remove(&hash);
+        Ok(hash)
+    }
+}
+
+impl ProcessPolicy for FileHashHelper {
+    fn should_process(&self, path: &Path) -> Result<bool> {
+        self.should_process_with(path, Self::needs_processing)
+    }
+
+    /// Stores the record under the file's hash, counting consecutive attempts
+    /// that did not reach a final outcome.
+    fn record(&self, record: &ProcessRecord) -> Result<()> {
+        self.store_record(record).map(|_| ())
     }
 }
 
@@ -101,27 +248,7 @@ impl ProcessPolicy for FileHashHelper {
 #[cfg(test)]
 impl FileHashHelper {
     pub fn new_with_path(store_path: std::path::PathBuf) -> anyhow::Result<Self> {
-        use std::collections::HashSet;
-        use std::fs::{File, OpenOptions};
-        use std::io::{BufRead, BufReader};
-        use std::sync::Mutex;
-
-        if !store_path.exists() {
-            File::create(&store_path)?;
-        }
-
-        let reader = BufReader::new(File::open(&store_path)?);
-        let mut set = HashSet::new();
-        for line in reader.lines().flatten() {
-            set.insert(line);
-        }
-
-        let file = OpenOptions::new().append(true).open(&store_path)?;
-
-        Ok(Self {
-            hashes: Mutex::new(set),
-            store_file: Mutex::new(file),
-        })
+        Ok(Self::with_store(OutcomeStore::open(&store_path)?))
     }
 }
 
@@ -138,7 +265,7 @@ mod test_file_hash_helper {
 
     fn make_test_helper(tmp_dir: &Path) -> Result<FileHashHelper> {
         let mut store = tmp_dir.to_path_buf();
-        store.push(FileHashHelper::FILENAME);
+        store.push(OutcomeStore::FILENAME);
         FileHashHelper::new_with_path(store)
     }
 
@@ -156,6 +283,46 @@ mod test_file_hash_helper {
         Ok(())
     }
 
+    #[test]
+    fn test_audio_hash_ignores_tag_edits() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let helper = make_test_helper(tmp_dir.path())?.with_mode(HashMode::Audio);
+        let song = tmp_dir.path().join("song.mp3");
+        fs::copy(DEFAULT_SONG_NAME, &song)?;
+
+        let audio_before = helper.hash_file(&song)?;
+        let content_before = FileHashHelper::hash_content(&song)?;
+        assert!(audio_before.starts_with("audio:"));
+
+        let mut tag = id3::Tag::read_from_path(&song)?;
+        id3::TagLike::set_artist(&mut tag, "Somebody Else");
+        id3::TagLike::set_title(&mut tag, "A much longer title than the original one");
+        tag.write_to_path(&song, tag.version())?;
+
+        assert_ne!(FileHashHelper::hash_content(&song)?, content_before);
+        assert_eq!(helper.hash_file(&song)?, audio_before);
+        Ok(())
+    }
+
+    #[test]
+    fn test_audio_hash_falls_back_to_content() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let helper = make_test_helper(tmp_dir.path())?.with_mode(HashMode::Audio);
+        let not_audio = tmp_dir.path().join("notes.txt");
+        fs::write(&not_audio, "not audio")?;
+
+        assert!(FileHashHelper::hash_audio(&not_audio).is_err());
+        assert_eq!(helper.hash_file(&not_audio)?, FileHashHelper::hash_content(&not_audio)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_mode_names() {
+        assert_eq!(HashMode::from_name("Audio"), Some(HashMode::Audio));
+        assert_eq!(HashMode::from_name("content"), Some(HashMode::Content));
+        assert_eq!(HashMode::from_name("video"), None);
+    }
+
     #[test]
     fn test_should_process_logic() -> Result<()> {
         let mp3_path = PathBuf::from(DEFAULT_SONG_NAME);
@@ -173,13 +340,14 @@ mod test_file_hash_helper {
         let mp3_path = PathBuf::from(DEFAULT_SONG_NAME);
         let tmp_dir = tempfile::tempdir()?;
 
-        // First run → store hash
+        // First run → process and record the outcome
         {
             let helper = make_test_helper(tmp_dir.path())?;
             assert!(helper.should_process(&mp3_path)?);
+            helper.record(&ProcessRecord::new(&mp3_path, Outcome::Written))?;
         }
 
-        // Second instance should reload hashes from file
+        // Second instance should reload outcomes from the store
         {
             let helper = make_test_helper(tmp_dir.path())?;
             assert!(!helper.should_process(&mp3_path)?);
@@ -188,6 +356,80 @@ mod test_file_hash_helper {
         Ok(())
     }
 
+    #[test]
+    fn test_unrecorded_files_are_processed_again() -> Result<()> {
+        let mp3_path = PathBuf::from(DEFAULT_SONG_NAME);
+        let tmp_dir = tempfile::tempdir()?;
+
+        // A crash between `should_process` and `record` must not lose the file
+        {
+            let helper = make_test_helper(tmp_dir.path())?;
+            assert!(helper.should_process(&mp3_path)?);
+        }
+
+        let helper = make_test_helper(tmp_dir.path())?;
+        assert!(helper.should_process(&mp3_path)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decision_depends_on_outcome() -> Result<()> {
+        let mp3_path = PathBuf::from(DEFAULT_SONG_NAME);
+        let tmp_dir = tempfile::tempdir()?;
+        let helper = make_test_helper(tmp_dir.path())?;
+
+        for (outcome, expected) in [
+            (Outcome::Error, true),
+            (Outcome::NotFound, false),
+            (Outcome::Instrumental, false),
+            (Outcome::Written, false),
+        ] {
+            helper.store.put(&helper.hash_file(&mp3_path)?, &ProcessRecord::new(&mp3_path, outcome))?;
+            assert_eq!(helper.should_process(&mp3_path)?, expected, "{:?}", outcome);
+            if expected {
+                helper.record(&ProcessRecord::new(&mp3_path, outcome))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_counts_unsuccessful_attempts() -> Result<()> {
+        let mp3_path = PathBuf::from(DEFAULT_SONG_NAME);
+        let tmp_dir = tempfile::tempdir()?;
+        let helper = make_test_helper(tmp_dir.path())?;
+        let hash = helper.hash_file(&mp3_path)?;
+        let attempts = || -> Result<u32> { Ok(helper.store.get(&hash)?.unwrap().attempts) };
+
+        helper.record(&ProcessRecord::new(&mp3_path, Outcome::NotFound))?;
+        assert_eq!(attempts()?, 1);
+        helper.record(&ProcessRecord::new(&mp3_path, Outcome::Error))?;
+        assert_eq!(attempts()?, 2);
+        helper.record(&ProcessRecord::new(&mp3_path, Outcome::Written))?;
+        assert_eq!(attempts()?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_hashes_are_migrated() -> Result<()> {
+        let mp3_path = PathBuf::from(DEFAULT_SONG_NAME);
+        let tmp_dir = tempfile::tempdir()?;
+        let legacy_path = tmp_dir.path().join(FileHashHelper::FILENAME);
+
+        let helper = make_test_helper(tmp_dir.path())?;
+        fs::write(&legacy_path, format!("{}\n", helper.hash_file(&mp3_path)?))?;
+        FileHashHelper::migrate_legacy_store(&helper.store, &legacy_path)?;
+
+        assert!(!legacy_path.exists());
+        assert!(tmp_dir.path().join("processed_hashes.txt.migrated").exists());
+        assert!(!helper.should_process(&mp3_path)?);
+
+        Ok(())
+    }
+
     #[test]
     fn test_parallel_safety() -> Result<()> {
         use rayon::prelude::*;
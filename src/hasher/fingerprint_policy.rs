@@ -0,0 +1,235 @@
+use crate::hasher::cooldown_policy::CooldownPolicy;
+use crate::hasher::file_hash_helper::HashMode;
+use crate::store::outcome_store::{now, Fingerprint, ProcessRecord};
+use crate::traits::traits::ProcessPolicy;
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+/// Fast path in front of [`CooldownPolicy`]: the content hash stored for a
+/// path is reused while its size, mtime and inode are unchanged, so unchanged
+/// files are never read. With `verify` every file is fully hashed again.
+pub struct FingerprintPolicy {
+    policy: CooldownPolicy,
+    verify: bool,
+}
+
+impl FingerprintPolicy {
+    pub fn new(policy: CooldownPolicy, verify: bool) -> Self {
+        Self { policy, verify }
+    }
+
+    pub fn new_with_trait(policy: CooldownPolicy, verify: bool) -> Arc<dyn ProcessPolicy> {
+        Arc::new(Self::new(policy, verify))
+    }
+
+    /// Size, mtime and inode of `path`, paired with `hash` computed in `mode`.
+    pub fn fingerprint(path: &Path, hash: String, mode: HashMode) -> Result<Fingerprint> {
+        let metadata = fs::metadata(path).with_context(|| format!("Failed to stat file: {:?}", path))?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or(0);
+        Ok(Fingerprint {
+            size: metadata.len(),
+            modified,
+            inode: Self::inode(&metadata),
+            hash,
+            mode,
+        })
+    }
+
+    #[cfg(unix)]
+    fn inode(metadata: &fs::Metadata) -> u64 {
+        std::os::unix::fs::MetadataExt::ino(metadata)
+    }
+
+    #[cfg(not(unix))]
+    fn inode(_metadata: &fs::Metadata) -> u64 {
+        0
+    }
+
+    /// Fingerprints are keyed by absolute path so relative invocations share them.
+    fn key(path: &Path) -> PathBuf {
+        fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+    }
+
+    /// The stored hash when the fingerprint still matches, otherwise a fresh
+    /// SHA-256 which is then stored with the new fingerprint.
+    fn content_hash(&self, path: &Path) -> Result<String> {
+        let hashes = self.policy.hashes();
+        let store = hashes.store();
+        let key = Self::key(path);
+        let stored = store
+            .get_fingerprint(&key)?
+            .filter(|stored| stored.mode == hashes.mode());
+        let current = Self::fingerprint(path, String::new(), hashes.mode())?;
+
+        if let Some(stored) = &stored
+            && !self.verify
+            && stored.matches(&current)
+        {
+            debug!("Fingerprint unchanged for {}, reusing hash", path.display());
+            return Ok(stored.hash.clone());
+        }
+
+        let hash = hashes.hash_file(path)?;
+        if let Some(stored) = &stored
+            && stored.matches(&current)
+            && stored.hash != hash
+        {
+            warn!("Content of {} changed without a size or mtime change", path.display());
+        }
+        store.put_fingerprint(&key, &Fingerprint { hash: hash.clone(), ..current })?;
+        Ok(hash)
+    }
+}
+
+impl ProcessPolicy for FingerprintPolicy {
+    fn should_process(&self, path: &Path) -> Result<bool> {
+        let hash = self.content_hash(path)?;
+        let now = now();
+        self.policy
+            .hashes()
+            .should_process_hash(path, hash, |record| self.policy.is_due(record, now))
+    }
+
+    /// Embedding lyrics rewrites the song, so its new fingerprint is stored
+    /// with the new hash and the next run need not read it again.
+    fn record(&self, record: &ProcessRecord) -> Result<()> {
+        let hashes = self.policy.hashes();
+        let hash = hashes.store_record(record)?;
+        if record.embedded {
+            let fingerprint = Self::fingerprint(&record.path, hash, hashes.mode())?;
+            hashes.store().put_fingerprint(&Self::key(&record.path), &fingerprint)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_fingerprint_policy {
+    use super::*;
+    use crate::hasher::file_hash_helper::FileHashHelper;
+    use crate::model::data_model::{FieldSources, Lyric, Song, SongMetadata, Writer, WriterOptions};
+    use crate::store::outcome_store::{Outcome, OutcomeStore};
+
+    const DEFAULT_SONG_NAME: &str = "test_resources/benny_blanco-roses.mp3";
+
+    fn make_policy(store_path: &Path, verify: bool) -> Result<FingerprintPolicy> {
+        let helper = FileHashHelper::with_store(OutcomeStore::open(store_path)?);
+        let cooldown = CooldownPolicy::with_helper(helper, CooldownPolicy::DEFAULT_COOLDOWN);
+        Ok(FingerprintPolicy::new(cooldown, verify))
+    }
+
+    /// Copies the fixture so the tests can touch and rewrite it.
+    fn copy_song(tmp_dir: &Path) -> Result<PathBuf> {
+        let song = tmp_dir.join("song.mp3");
+        fs::copy(DEFAULT_SONG_NAME, &song)?;
+        Ok(song)
+    }
+
+    #[test]
+    fn unchanged_files_reuse_the_stored_hash() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let song = copy_song(tmp_dir.path())?;
+        let policy = make_policy(&tmp_dir.path().join(OutcomeStore::FILENAME), false)?;
+        let real_hash = policy.policy.hashes().hash_file(&song)?;
+
+        assert_eq!(policy.content_hash(&song)?, real_hash);
+
+        // A bogus stored hash proves the file is not read again.
+        let key = FingerprintPolicy::key(&song);
+        let fingerprint = FingerprintPolicy::fingerprint(&song, "stale".to_owned(), HashMode::Content)?;
+        policy.policy.hashes().store().put_fingerprint(&key, &fingerprint)?;
+        assert_eq!(policy.content_hash(&song)?, "stale");
+
+        let verifying = FingerprintPolicy { verify: true, ..policy };
+        assert_eq!(verifying.content_hash(&song)?, real_hash);
+
+        // Fingerprints taken in another hash mode are not reused.
+        let audio = CooldownPolicy::with_helper(
+            FileHashHelper::with_store(OutcomeStore::open(&tmp_dir.path().join("audio.redb"))?).with_mode(HashMode::Audio),
+            CooldownPolicy::DEFAULT_COOLDOWN,
+        );
+        audio.hashes().store().put_fingerprint(&key, &fingerprint)?;
+        let audio = FingerprintPolicy::new(audio, false);
+        assert!(audio.content_hash(&song)?.starts_with("audio:"));
+        Ok(())
+    }
+
+    #[test]
+    fn changed_files_are_rehashed() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let song = copy_song(tmp_dir.path())?;
+        let policy = make_policy(&tmp_dir.path().join(OutcomeStore::FILENAME), false)?;
+
+        let before = policy.content_hash(&song)?;
+        let mut bytes = fs::read(&song)?;
+        bytes.push(0);
+        fs::write(&song, bytes)?;
+
+        let after = policy.content_hash(&song)?;
+        assert_ne!(before, after);
+        assert_eq!(after, policy.policy.hashes().hash_file(&song)?);
+        Ok(())
+    }
+
+    #[test]
+    fn outcomes_are_shared_with_content_hash() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let song = copy_song(tmp_dir.path())?;
+        let store_path = tmp_dir.path().join(OutcomeStore::FILENAME);
+        {
+            let policy = make_policy(&store_path, false)?;
+            assert!(policy.should_process(&song)?);
+            policy.record(&ProcessRecord::new(&song, Outcome::Written))?;
+        }
+
+        {
+            let policy = make_policy(&store_path, false)?;
+            assert!(!policy.should_process(&song)?);
+        }
+        let verifying = make_policy(&store_path, true)?;
+        assert!(!verifying.should_process(&song)?);
+        Ok(())
+    }
+
+    #[test]
+    fn embedded_lyrics_do_not_make_the_song_look_new() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let song_path = copy_song(tmp_dir.path())?;
+        let store_path = tmp_dir.path().join(OutcomeStore::FILENAME);
+        let song = Song::new(song_path.to_str().unwrap()).unwrap();
+        let lyric = Lyric { lyric: String::from("[00:01.00]Line"), song: song.clone() };
+        let md = SongMetadata {
+            song,
+            artist: String::from("Benny Blanco"),
+            title: String::from("Roses"),
+            album_title: String::new(),
+            duration: None,
+            sources: FieldSources::default(),
+        };
+        let options = WriterOptions { sidecar: false, embed: true, ..WriterOptions::default() };
+        {
+            let policy = make_policy(&store_path, false)?;
+            let before = policy.policy.hashes().hash_file(&song_path)?;
+            assert!(policy.should_process(&song_path)?);
+            assert_eq!(Writer::write_outputs(&lyric, &md, &options), vec![song_path.clone()]);
+            assert_ne!(policy.policy.hashes().hash_file(&song_path)?, before);
+
+            let mut record = ProcessRecord::new(&song_path, Outcome::Written);
+            record.embedded = true;
+            policy.record(&record)?;
+        }
+
+        assert!(!make_policy(&store_path, false)?.should_process(&song_path)?);
+        assert!(!make_policy(&store_path, true)?.should_process(&song_path)?);
+        Ok(())
+    }
+}
@@ -1,2 +1,4 @@
 pub mod file_hash_helper;
-pub mod dummy_hasher;
\ No newline at end of file
+pub mod dummy_hasher;
+pub mod cooldown_policy;
+pub mod fingerprint_policy;
\ No newline at end of file
@@ -1,8 +1,13 @@
+// The crate is named after the project, `GetLyrics`; this lint only concerns that name.
+#![allow(non_snake_case)]
+
 pub mod model;
 pub mod api;
 pub mod metadata;
 pub mod writer;
 pub mod traits;
 pub mod hasher;
+pub mod store;
+pub mod config;
 
-pub mod parallel_helper;
\ No newline at end of file
+pub mod parallel_helper;
@@ -1,135 +1,654 @@
+mod cli;
+
 use rayon::iter::ParallelIterator;
-use std::env;
-use std::path::Path;
-use std::process::exit;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::Arc;
+use clap::CommandFactory;
+use clap_complete::Shell;
 use env_logger::{Builder, Env};
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use rayon::prelude::IntoParallelRefIterator;
 use rayon::ThreadPoolBuilder;
+use GetLyrics::api::lyric_error::LyricError;
 use GetLyrics::api::lrclib_api::LrcLibAPI;
+use GetLyrics::config::config::Config;
 use GetLyrics::api::lyric_api::LyricApi;
+use GetLyrics::api::provider_chain::{ProviderChain, ProviderOptions};
 use GetLyrics::hasher::dummy_hasher::DummyHasher;
+use GetLyrics::hasher::cooldown_policy::CooldownPolicy;
 use GetLyrics::hasher::file_hash_helper::FileHashHelper;
+use GetLyrics::hasher::fingerprint_policy::FingerprintPolicy;
 use GetLyrics::metadata::metadata_extractor::MetadataExtractor;
-use GetLyrics::model::data_model::{Lyric, Song, Writer};
+use GetLyrics::metadata::path_pattern::PathPattern;
+use GetLyrics::model::data_model::{Lyric, Song, SongMetadata, Writer, WriterOptions};
+use GetLyrics::model::lrc::LrcDocument;
+use GetLyrics::model::lrc_validation::LrcValidator;
+use GetLyrics::model::run_summary::RunSummary;
 use GetLyrics::parallel_helper::parallel_helper::ParallelHelper;
-use GetLyrics::traits::traits::{LyricIface, ProcessPolicy};
+use GetLyrics::store::lookup_cache::{CacheKey, CacheTtl, CachedAnswer, CachedLookup, LookupCache};
+use GetLyrics::store::outcome_store::{now, Outcome, OutcomeStore, ProcessRecord};
+use GetLyrics::traits::traits::ProcessPolicy;
 
-fn main() {
-    // limit API pressure requests
-    ThreadPoolBuilder::new().num_threads(6).build_global().unwrap();
+use crate::cli::{
+    CacheCommand, CacheFilter, CacheTtlArgs, Cli, Command, ConfigCommand, ConvertArgs, DbCommand, FetchOptions, ShiftArgs,
+    StatusArgs,
+};
 
+/// Everything a worker needs to process one file.
+struct RunContext {
+    providers: ProviderChain,
+    hasher: Arc<dyn ProcessPolicy>,
+    writer_options: WriterOptions,
+    path_patterns: Vec<PathPattern>,
+    validator: LrcValidator,
+    summary: RunSummary,
+}
 
+fn main() -> ExitCode {
     let env = Env::new().filter_or("RUST_LOG", "info");
     Builder::from_env(env).init();
 
-    let args: Vec<String> = env::args().collect();
+    let cli = Cli::parse_with_default();
+    // These never read the configuration, so a broken one does not stop them.
+    let command = match cli.command {
+        Command::Shift(args) => return run_shift(args),
+        Command::Convert(args) => return run_convert(args),
+        Command::Completions { shell } => return run_completions(shell),
+        command => command,
+    };
+    let config = match Config::load(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{:#}", e);
+            return ExitCode::FAILURE;
+        }
+    };
 
-    if args.len() < 2 {
-        error!("Usage: GetLyrics [-r|--recursive] [-k|--karaoke] [-f|--force] <file_or_folder>");
-        exit(1);
+    match command {
+        Command::Fetch(args) => run_fetch(&args.paths, args.recursive, args.options, &config),
+        Command::Scan(args) => run_fetch(&args.folders, true, args.options, &config),
+        Command::Status(args) => run_status(args, &config),
+        Command::Cache(command) => run_cache(command, &config),
+        Command::Db(command) => run_db(command, &config),
+        Command::Config(command) => run_config(command, cli.config.as_deref(), config),
+        Command::Shift(_) | Command::Convert(_) | Command::Completions { .. } => unreachable!(),
     }
-    let mut hasher: Arc<dyn ProcessPolicy> = FileHashHelper::new_with_trait().expect("Failed to create file hasher");
+}
 
+fn run_completions(shell: Shell) -> ExitCode {
+    // Rendered first so a closed pipe (`| head`) is not a panic.
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut Cli::command(), "getlyrics", &mut script);
+    let _ = io::stdout().write_all(&script);
+    ExitCode::SUCCESS
+}
 
-    let mut karaoke = false;
-    let mut recursive = false;
-    let mut force_scan = false;
-    let mut path: Option<String> = None;
+/// `fetch` and `scan` subcommands. Flags override the configuration. Fails
+/// when a path is invalid or a song could not be processed.
+fn run_fetch(paths: &[PathBuf], recursive: bool, options: FetchOptions, config: &Config) -> ExitCode {
+    // limit API pressure requests
+    let threads = options.threads.map(usize::from).unwrap_or(config.threads);
+    ThreadPoolBuilder::new().num_threads(threads).build_global().unwrap();
 
-    for arg in &args[1..] {
-        match arg.as_str() {
-            "-k" | "--karaoke" => karaoke = true,
-            "-r" | "--recursive" => recursive = true,
-            "-f" | "--force" => force_scan = true,
-            _ => path = Some(arg.clone()),
+    let state_dir = match config.state_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            error!("{:#}", e);
+            return ExitCode::FAILURE;
         }
-    }
-
-    let Some(path) = path else {
-        panic!("Usage: GetLyrics [-r|--recursive] [-k|--karaoke] [-f|--force] <file_or_folder>");
     };
 
-    let path_obj = Path::new(&path);
+    let mut writer_options = config.writer_options().expect("validated by Config::load");
+    if options.no_header {
+        writer_options.id_tags = false;
+    }
+    if !options.subtitles.is_empty() {
+        writer_options.subtitles = options.subtitles;
+    }
+    writer_options.word_timings |= options.word_timings;
+    if let Some(overwrite) = options.overwrite {
+        writer_options.overwrite = overwrite;
+    }
+    // Output flags replace the configured outputs.
+    if options.sidecar || options.embed {
+        writer_options.sidecar = options.sidecar;
+        writer_options.embed = options.embed;
+    }
 
-    if force_scan {
+    let hasher: Arc<dyn ProcessPolicy> = if options.force {
         info!("Running force scan.");
-        hasher = DummyHasher::new();
+        DummyHasher::new()
+    } else {
+        let cooldown = options.cooldown_days.unwrap_or(config.cooldown());
+        let hash_mode = options.hash_mode.unwrap_or(config.hash_mode().expect("validated by Config::load"));
+        let hashes = match FileHashHelper::in_dir(&state_dir) {
+            Ok(hashes) => hashes.with_mode(hash_mode),
+            Err(e) => {
+                error!("Failed to create file hasher: {:#}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        let policy = CooldownPolicy::with_helper(hashes, cooldown);
+        FingerprintPolicy::new_with_trait(policy, options.verify_hashes || config.state.verify_hashes)
+    };
+
+    let mut provider_options = config.provider_options().expect("validated by Config::load");
+    provider_options.karaoke |= options.karaoke;
+    if let Some(url) = &options.lrclib_url {
+        provider_options.set_base_url(LrcLibAPI::NAME, url);
+    }
+    if let Some(url) = &options.lyricsovh_url {
+        provider_options.set_base_url(LyricApi::NAME, url);
+    }
+    if options.search_threshold.is_some() {
+        provider_options.search_threshold = options.search_threshold;
+    }
+    if options.duration_tolerance.is_some() {
+        provider_options.duration_tolerance = options.duration_tolerance;
+    }
+    if let Some(proxy) = &options.proxy {
+        provider_options.http = provider_options.http.with_proxy(proxy);
+    }
+    if let Some(user_agent) = &options.user_agent {
+        provider_options.http = provider_options.http.with_user_agent(user_agent);
+    }
+    if let Some(timeout) = options.connect_timeout {
+        provider_options.http = provider_options.http.with_connect_timeout(timeout);
+    }
+    if let Some(timeout) = options.timeout {
+        provider_options.http = provider_options.http.with_timeout(timeout);
+    }
+    // Settings for every provider first, so that per-provider ones win. They
+    // also replace per-provider values from the configuration.
+    for setting in options.rate_limit.iter().filter(|setting| setting.provider.is_none()) {
+        for throttle in provider_options.throttles.values_mut().chain([&mut provider_options.throttle]) {
+            *throttle = throttle.clone().with_rate(setting.value).expect("validated by the parser");
+        }
+    }
+    for setting in options.retries.iter().filter(|setting| setting.provider.is_none()) {
+        for throttle in provider_options.throttles.values_mut().chain([&mut provider_options.throttle]) {
+            throttle.retry = throttle.retry.clone().with_max_retries(setting.value);
+        }
+    }
+    for setting in &options.rate_limit {
+        if let Some(provider) = &setting.provider {
+            let throttle = provider_options
+                .throttle(provider)
+                .clone()
+                .with_rate(setting.value)
+                .expect("validated by the parser");
+            provider_options.set_throttle(provider, throttle);
+        }
+    }
+    for setting in &options.retries {
+        if let Some(provider) = &setting.provider {
+            let mut throttle = provider_options.throttle(provider).clone();
+            throttle.retry = throttle.retry.with_max_retries(setting.value);
+            provider_options.set_throttle(provider, throttle);
+        }
+    }
+    let mut providers = match build_provider_chain(config, &provider_options) {
+        Ok(providers) => providers,
+        Err(e) => {
+            error!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    // The cache keeps answering when rerunning with --force; --no-cache asks the providers again.
+    if !options.no_cache && config.cache.enabled {
+        match LookupCache::open(&state_dir.join(LookupCache::FILENAME)) {
+            Ok(cache) => {
+                let variant = if provider_options.karaoke { "synced" } else { "plain" };
+                providers = providers.with_cache(Arc::new(cache), cache_ttl(&options.cache_ttl, config), variant);
+            }
+            Err(e) => warn!("Running without the lookup cache: {}", e),
+        }
     }
 
-    if path_obj.is_dir() {
-        process_directory(path_obj, karaoke, recursive, hasher);
-    } else if path_obj.is_file() {
-        process_single_file(path_obj, karaoke, hasher);
+    // Patterns given on the command line replace the configured ones.
+    let path_patterns: Vec<PathPattern> = if options.path_pattern.is_empty() {
+        config.path_patterns().expect("validated by Config::load")
     } else {
-        panic!("Invalid path: {}", path);
-    }
-}
+        options.path_pattern
+    };
 
-fn process_directory(dir: &Path, karaoke: bool, recursive: bool, hasher: Arc<dyn ProcessPolicy>) {
-    info!("Scanning directory: {}", dir.display());
+    let validator = LrcValidator::default()
+        .with_repair(options.repair_lyrics || config.lyrics.repair)
+        .with_duration_tolerance(
+            provider_options
+                .duration_tolerance
+                .unwrap_or(LrcDocument::DURATION_TOLERANCE),
+        );
 
-    let files = ParallelHelper::collect_audio_files(dir, recursive);
+    let context = RunContext {
+        providers,
+        hasher,
+        writer_options,
+        path_patterns,
+        validator,
+        summary: RunSummary::new(),
+    };
 
+    let Some(files) = collect_paths(paths, |dir| {
+        info!("Scanning directory: {}", dir.display());
+        ParallelHelper::collect_audio_files(dir, recursive)
+    }) else {
+        return ExitCode::FAILURE;
+    };
     files.par_iter().for_each(|path| {
-        process_single_file(path, karaoke, hasher.clone());
+        process_single_file(path, &context);
     });
+    info!("Run summary: {}", context.summary);
+    if context.summary.failed() > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Files given directly plus what `walk` finds in the given folders. Logs
+/// every path that is neither and returns `None` if there was one.
+fn collect_paths(paths: &[PathBuf], walk: impl Fn(&Path) -> Vec<PathBuf>) -> Option<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut valid = true;
+    for path in paths {
+        if path.is_dir() {
+            files.extend(walk(path));
+        } else if path.is_file() {
+            files.push(path.clone());
+        } else {
+            error!("Invalid path: {}", path.display());
+            valid = false;
+        }
+    }
+    valid.then_some(files)
+}
+
+fn cache_ttl(args: &CacheTtlArgs, config: &Config) -> CacheTtl {
+    let defaults = config.cache_ttl();
+    CacheTtl {
+        hit: args.cache_ttl.unwrap_or(defaults.hit),
+        miss: args.cache_miss_ttl.unwrap_or(defaults.miss),
+    }
+}
+
+/// Whether `path` is one of `roots` or inside one of them. No roots select everything.
+fn is_under(path: &Path, roots: &[PathBuf]) -> bool {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    roots.is_empty() || roots.iter().any(|root| path.starts_with(root))
+}
+
+fn canonical_roots(paths: &[PathBuf]) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .map(|path| path.canonicalize().unwrap_or_else(|_| path.clone()))
+        .collect()
+}
+
+fn open_state_store(config: &Config) -> Option<OutcomeStore> {
+    config
+        .state_dir()
+        .and_then(|dir| OutcomeStore::open(&dir.join(OutcomeStore::FILENAME)))
+        .inspect_err(|e| error!("{:#}", e))
+        .ok()
+}
+
+/// `status` subcommand: the latest outcome of every song recorded under the given paths.
+fn run_status(args: StatusArgs, config: &Config) -> ExitCode {
+    let Some(store) = open_state_store(config) else {
+        return ExitCode::FAILURE;
+    };
+    let records = match store.all() {
+        Ok(records) => records,
+        Err(e) => {
+            error!("{:#}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let roots = canonical_roots(&args.paths);
+    // A song whose content changed has a record per version; show the latest.
+    let mut latest: HashMap<PathBuf, ProcessRecord> = HashMap::new();
+    for (_, record) in records {
+        if record.path.as_os_str().is_empty() || !is_under(&record.path, &roots) {
+            continue;
+        }
+        match latest.get(&record.path) {
+            Some(known) if known.timestamp >= record.timestamp => {}
+            _ => {
+                latest.insert(record.path.clone(), record);
+            }
+        }
+    }
+
+    let mut records: Vec<ProcessRecord> = latest.into_values().collect();
+    records.sort_by(|a, b| a.path.cmp(&b.path));
+    if args.list {
+        for record in &records {
+            let provider = record.provider.as_deref().unwrap_or("-");
+            let kind = if record.synced { "synced" } else { "plain" };
+            match record.outcome {
+                Outcome::Written => println!("{}\t{} ({}, {})", record.path.display(), record.outcome, provider, kind),
+                _ => println!("{}\t{}", record.path.display(), record.outcome),
+            }
+        }
+    }
+    let count = |outcome: Outcome| records.iter().filter(|record| record.outcome == outcome).count();
+    info!(
+        "{} songs: {} written ({} synced), {} not found, {} instrumental, {} failed",
+        records.len(),
+        count(Outcome::Written),
+        records.iter().filter(|record| record.outcome == Outcome::Written && record.synced).count(),
+        count(Outcome::NotFound),
+        count(Outcome::Instrumental),
+        count(Outcome::Error)
+    );
+    ExitCode::SUCCESS
 }
 
-fn process_single_file(path: &Path, karaoke: bool, hasher: Arc<dyn ProcessPolicy>) {
+/// `db` subcommand: low level access to the record of processed songs.
+fn run_db(command: DbCommand, config: &Config) -> ExitCode {
+    if let DbCommand::Path = command {
+        return match config.state_dir() {
+            Ok(dir) => {
+                println!("{}", dir.join(OutcomeStore::FILENAME).display());
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                error!("{:#}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+    let Some(store) = open_state_store(config) else {
+        return ExitCode::FAILURE;
+    };
+    let records = match store.all() {
+        Ok(records) => records,
+        Err(e) => {
+            error!("{:#}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    match command {
+        DbCommand::Path => unreachable!(),
+        DbCommand::Export => {
+            for (hash, record) in records {
+                println!("{}", serde_json::json!({ "hash": hash, "record": record }));
+            }
+        }
+        DbCommand::Forget { paths } => {
+            let roots = canonical_roots(&paths);
+            let mut forgotten = 0;
+            for (hash, record) in records {
+                if record.path.as_os_str().is_empty() || !is_under(&record.path, &roots) {
+                    continue;
+                }
+                match store.remove(&hash) {
+                    Ok(_) => forgotten += 1,
+                    Err(e) => {
+                        error!("{:#}", e);
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            info!("Forgot {} records", forgotten);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// `cache` subcommand: shows or removes cached provider answers.
+fn run_cache(command: CacheCommand, config: &Config) -> ExitCode {
+    let cache = match config.state_dir().and_then(|dir| LookupCache::open(&dir.join(LookupCache::FILENAME))) {
+        Ok(cache) => cache,
+        Err(e) => {
+            error!("{:#}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let now = now();
+    let (CacheCommand::Stats(filter) | CacheCommand::List(filter) | CacheCommand::Purge(filter)) = &command;
+    let CacheFilter { provider, misses, expired, cache_ttl: ttl_args, text } = filter;
+    let ttl = cache_ttl(ttl_args, config);
+    let text = text.as_ref().map(|text| text.to_lowercase());
+    let selected = |key: &CacheKey, lookup: &CachedLookup| {
+        provider.as_ref().is_none_or(|provider| &key.provider == provider)
+            && (!misses || !lookup.answer.is_hit())
+            && (!expired || !lookup.is_fresh(&ttl, now))
+            && text.as_ref().is_none_or(|text| key.artist.contains(text) || key.title.contains(text))
+    };
+
+    if let CacheCommand::Purge(_) = command {
+        return match cache.purge(selected) {
+            Ok(removed) => {
+                info!("Removed {} cached answers", removed);
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                error!("{:#}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+    let entries = match cache.all() {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("{:#}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let entries: Vec<_> = entries.into_iter().filter(|(key, lookup)| selected(key, lookup)).collect();
+    if let CacheCommand::List(_) = command {
+        for (key, lookup) in &entries {
+            let answer = match &lookup.answer {
+                CachedAnswer::Found { .. } => "found",
+                CachedAnswer::NotFound => "not found",
+                CachedAnswer::Instrumental => "instrumental",
+            };
+            let state = if lookup.is_fresh(&ttl, now) { "" } else { ", expired" };
+            let age = now.saturating_sub(lookup.timestamp) / (60 * 60);
+            println!("{} ({}, {}h old{})", key, answer, age, state);
+        }
+    }
+    let hits = entries.iter().filter(|(_, lookup)| lookup.answer.is_hit()).count();
+    let stale = entries.iter().filter(|(_, lookup)| !lookup.is_fresh(&ttl, now)).count();
+    info!(
+        "{} cached answers: {} hits, {} misses, {} expired",
+        entries.len(),
+        hits,
+        entries.len() - hits,
+        stale
+    );
+    ExitCode::SUCCESS
+}
+
+/// `config` subcommand: what the configuration file and the environment set up.
+fn run_config(command: ConfigCommand, explicit: Option<&Path>, mut config: Config) -> ExitCode {
+    match command {
+        ConfigCommand::Show => {
+            // Spell out where the state goes even when it is the default.
+            match config.state_dir() {
+                Ok(dir) => config.state.dir = Some(dir),
+                Err(e) => warn!("{:#}", e),
+            }
+            match config.to_toml() {
+                Ok(text) => print!("{}", text),
+                Err(e) => {
+                    error!("{:#}", e);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        ConfigCommand::Path => match Config::path(explicit) {
+            Some(path) => {
+                println!("{}", path.display());
+                if !path.exists() {
+                    info!("{} does not exist, the defaults apply", path.display());
+                }
+            }
+            None => {
+                error!("Could not resolve the configuration directory");
+                return ExitCode::FAILURE;
+            }
+        },
+    }
+    ExitCode::SUCCESS
+}
+
+/// `convert` subcommand: writes subtitles from the `.lrc` files already next
+/// to the songs, without fetching anything.
+fn run_convert(args: ConvertArgs) -> ExitCode {
+    let Some(songs) = collect_paths(&args.paths, |dir| ParallelHelper::collect_audio_files(dir, args.recursive)) else {
+        return ExitCode::FAILURE;
+    };
+
+    let mut converted = 0;
+    for song_path in &songs {
+        let lrc_path = song_path.with_extension("lrc");
+        let (Some(song), Ok(text)) = (Song::new(&song_path.to_string_lossy()), std::fs::read_to_string(&lrc_path)) else {
+            debug!("No .lrc file for {}", song_path.display());
+            continue;
+        };
+        let lyric = Lyric { lyric: text, song };
+        let duration = MetadataExtractor::duration(&lyric.song);
+        if !Writer::write_subtitles(&lyric, duration, &args.subtitles).is_empty() {
+            converted += 1;
+        }
+    }
+    info!("Wrote subtitles for {} of {} songs", converted, songs.len());
+    ExitCode::SUCCESS
+}
+
+/// `shift` subcommand: moves the timestamps of existing `.lrc` files.
+fn run_shift(args: ShiftArgs) -> ExitCode {
+    let Some(files) = collect_paths(&args.paths, |dir| ParallelHelper::collect_lrc_files(dir, args.recursive)) else {
+        return ExitCode::FAILURE;
+    };
+
+    let (mut shifted, mut failed) = (0, 0);
+    for file in &files {
+        match Writer::shift_lrc_file(file, args.millis, args.mode) {
+            Ok(true) => {
+                debug!("Shifted {} by {} ms", file.display(), args.millis);
+                shifted += 1;
+            }
+            Ok(false) => info!("Skipping {}, it has no synced lines", file.display()),
+            Err(e) => {
+                error!("{:#}", e);
+                failed += 1;
+            }
+        }
+    }
+    info!("Shifted {} of {} .lrc files by {} ms", shifted, files.len(), args.millis);
+    if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Provider order and strategy come from the configuration, where
+/// `GETLYRICS_PROVIDERS` and `GETLYRICS_STRATEGY` already overrode the file.
+fn build_provider_chain(config: &Config, options: &ProviderOptions) -> Result<ProviderChain, String> {
+    let names = config.provider_names().map_err(|e| e.to_string())?;
+    let strategy = config.strategy().map_err(|e| e.to_string())?;
+    let providers = ProviderChain::from_names(&names, strategy, options)?;
+    debug!("Provider chain: {:?} ({:?})", providers.provider_names(), providers.strategy());
+    Ok(providers)
+}
+
+fn process_single_file(path: &Path, context: &RunContext) {
 
 
     // --- NEW: skip if already processed ---
-    match hasher.should_process(path) {
+    match context.hasher.should_process(path) {
         Ok(false) => {
             debug!("Skipping already processed file: {}", path.display());
+            context.summary.record_skipped();
             return;
         }
         Err(e) => {
             error!("Hashing error for {}: {}", path.display(), e);
+            context.summary.record_outcome(Outcome::Error);
             return;
         }
         Ok(true) => {}
     }
+
+    let record = fetch_and_write(path, context);
+    context.summary.record_outcome(record.outcome);
+    if let Err(e) = context.hasher.record(&record) {
+        error!("Could not record outcome for {}: {}", path.display(), e);
+    }
+}
+
+fn fetch_and_write(path: &Path, context: &RunContext) -> ProcessRecord {
     let file_path = path.to_str().unwrap();
+    let mut record = ProcessRecord::new(path, Outcome::Error);
 
     let song = match Song::new(file_path) {
         Some(song) => song,
         None => {
             error!("Could not create Song from {}", file_path);
-            return;
+            return record;
         }
     };
 
     info!("Processing:\n\t {}", &song.filename);
 
-    match MetadataExtractor::extract(&song) {
-        Some(md) => {
-            let lrclib_api = if karaoke {
-                LrcLibAPI::new_karaoke_lyrics()
-            } else {
-                LrcLibAPI::new_plain_lyrics()
-            };
-
-            match lrclib_api.fetch_lyrics(&md) {
-                Ok(lyric) => write_lyric_to_file(&song, &lyric),
-                Err(_) => {
-                    let secondary = LyricApi::new().fetch_lyrics(&md);
-                    match secondary {
-                        Ok(lyric) => write_lyric_to_file(&song, &lyric),
-                        Err(e) => error!("{}", e),
-                    }
+    match MetadataExtractor::extract_with_patterns(&song, &context.path_patterns) {
+        Some(md) => match context.providers.fetch(&md) {
+            Ok(found) => {
+                debug!("Lyrics for {} provided by {}", &song.filename, found.provider);
+                record.provider = Some(found.provider.clone());
+                let lyric = validate_lyric(found.lyric, &md, context);
+                record.synced = lyric.is_synced();
+                let written = write_lyric_to_file(&song, &md, &lyric, &context.writer_options);
+                if let Some(output_path) = written.first() {
+                    record.outcome = Outcome::Written;
+                    record.output_path = Some(output_path.clone());
+                    record.embedded = written.contains(&song.filepath);
                 }
             }
-        }
+            Err(LyricError::Instrumental) => {
+                info!("Instrumental track, no lyrics: {}", &song.filename);
+                record.outcome = Outcome::Instrumental;
+            }
+            Err(LyricError::NotFound) => {
+                warn!("Lyrics not found for {}", &song.filename);
+                record.outcome = Outcome::NotFound;
+            }
+            Err(e) => error!("Could not fetch lyrics for {}: {}", &song.filename, e),
+        },
         None => error!("Could not extract metadata for {}", &song.filename),
     }
+    record
 }
 
-fn write_lyric_to_file(song: &Song, lyric: &Lyric) {
-    match Writer::write_lyric(&lyric) {
-        Some(_) => info!("SUCCESS: Lyrics written for: {}", &song.filename),
-        None => error!("Could not write lyrics file for song {}", &song.filename),
+/// Checks a synced lyric before it is written. Fatal problems keep only the text.
+fn validate_lyric(lyric: Lyric, md: &SongMetadata, context: &RunContext) -> Lyric {
+    let (lyric, report) = context.validator.apply(lyric, md);
+    context.summary.record_validation(&report);
+    if report.is_fatal() {
+        warn!("Writing plain lyrics for {}, the synced lyric is unusable: {}", &md.song.filename, report);
+    } else if !report.is_clean() {
+        warn!("Synced lyric for {}: {}", &md.song.filename, report);
+    } else if report.checked {
+        debug!("Synced lyric for {} is valid", &md.song.filename);
+    }
+    lyric
+}
+
+/// Returns every output written, the song itself when lyrics were embedded.
+fn write_lyric_to_file(song: &Song, md: &SongMetadata, lyric: &Lyric, options: &WriterOptions) -> Vec<PathBuf> {
+    let written = Writer::write_outputs(lyric, md, options);
+    if written.is_empty() {
+        error!("Could not write lyrics file for song {}", &song.filename);
+    } else {
+        info!("SUCCESS: Lyrics written for: {}", &song.filename);
     }
+    written
 }
@@ -0,0 +1,151 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+/// Tags and duration from the header of an ASF container (`.wma`), which
+/// symphonia can't demux.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AsfHeader {
+    pub title: String,
+    pub artist: String,
+    pub album_title: String,
+    /// Play duration minus preroll, in seconds.
+    pub duration: Option<f64>,
+}
+
+type Guid = [u8; 16];
+
+/// GUIDs in their on-disk byte order.
+const HEADER_OBJECT: Guid = guid(0x75B22630, 0x668E, 0x11CF, [0xA6, 0xD9, 0x00, 0xAA, 0x00, 0x62, 0xCE, 0x6C]);
+const FILE_PROPERTIES: Guid = guid(0x8CABDCA1, 0xA947, 0x11CF, [0x8E, 0xE4, 0x00, 0xC0, 0x0C, 0x20, 0x53, 0x65]);
+const CONTENT_DESCRIPTION: Guid = guid(0x75B22633, 0x668E, 0x11CF, [0xA6, 0xD9, 0x00, 0xAA, 0x00, 0x62, 0xCE, 0x6C]);
+const EXTENDED_CONTENT_DESCRIPTION: Guid =
+    guid(0xD2D0A440, 0xE307, 0x11D2, [0x97, 0xF0, 0x00, 0xA0, 0xC9, 0x5E, 0xA8, 0x50]);
+
+const fn guid(a: u32, b: u16, c: u16, d: [u8; 8]) -> Guid {
+    let a = a.to_le_bytes();
+    let b = b.to_le_bytes();
+    let c = c.to_le_bytes();
+    [a[0], a[1], a[2], a[3], b[0], b[1], c[0], c[1], d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7]]
+}
+
+/// Object header: GUID plus the size of the whole object, header included.
+const OBJECT_HEADER_LEN: u64 = 24;
+
+impl AsfHeader {
+    pub fn read(path: &Path) -> Result<AsfHeader> {
+        let file = File::open(path).with_context(|| format!("Failed to open file: {:?}", path))?;
+        Self::read_from(BufReader::new(file))
+    }
+
+    pub fn read_from<R: Read + Seek>(mut reader: R) -> Result<AsfHeader> {
+        let (id, _) = read_object_header(&mut reader)?;
+        if id != HEADER_OBJECT {
+            bail!("Not an ASF file");
+        }
+        let count = read_u32(&mut reader)?;
+        // Two reserved bytes.
+        reader.seek(SeekFrom::Current(2))?;
+
+        let mut header = AsfHeader::default();
+        for _ in 0..count {
+            let start = reader.stream_position()?;
+            let (id, size) = read_object_header(&mut reader)?;
+            if size < OBJECT_HEADER_LEN {
+                bail!("Corrupt ASF object size {}", size);
+            }
+            match id {
+                FILE_PROPERTIES => header.read_file_properties(&mut reader)?,
+                CONTENT_DESCRIPTION => header.read_content_description(&mut reader)?,
+                EXTENDED_CONTENT_DESCRIPTION => header.read_extended_content_description(&mut reader)?,
+                _ => {}
+            }
+            let end = start.checked_add(size).with_context(|| format!("Corrupt ASF object size {}", size))?;
+            reader.seek(SeekFrom::Start(end))?;
+        }
+        Ok(header)
+    }
+
+    fn read_file_properties<R: Read + Seek>(&mut self, reader: &mut R) -> Result<()> {
+        // File ID, file size, creation date and data packets count.
+        reader.seek(SeekFrom::Current(16 + 8 + 8 + 8))?;
+        let play_duration = read_u64(reader)?;
+        let _send_duration = read_u64(reader)?;
+        let preroll_ms = read_u64(reader)?;
+        if play_duration > 0 {
+            let seconds = play_duration as f64 / 10_000_000.0 - preroll_ms as f64 / 1000.0;
+            self.duration = Some(seconds.max(0.0));
+        }
+        Ok(())
+    }
+
+    fn read_content_description<R: Read>(&mut self, reader: &mut R) -> Result<()> {
+        let title_len = read_u16(reader)?;
+        let author_len = read_u16(reader)?;
+        // Copyright, description and rating lengths.
+        let mut others = [0u8; 6];
+        reader.read_exact(&mut others)?;
+        self.title = read_utf16(reader, title_len)?;
+        self.artist = read_utf16(reader, author_len)?;
+        Ok(())
+    }
+
+    fn read_extended_content_description<R: Read + Seek>(&mut self, reader: &mut R) -> Result<()> {
+        let count = read_u16(reader)?;
+        for _ in 0..count {
+            let name_len = read_u16(reader)?;
+            let name = read_utf16(reader, name_len)?;
+            let value_type = read_u16(reader)?;
+            let value_len = read_u16(reader)?;
+            // Type 0 is a UTF-16 string; every tag used here is one.
+            if value_type != 0 {
+                reader.seek(SeekFrom::Current(i64::from(value_len)))?;
+                continue;
+            }
+            let value = read_utf16(reader, value_len)?;
+            match name.as_str() {
+                "WM/AlbumTitle" => self.album_title = value,
+                "WM/AlbumArtist" if self.artist.is_empty() => self.artist = value,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_object_header<R: Read>(reader: &mut R) -> Result<(Guid, u64)> {
+    let mut id = [0u8; 16];
+    reader.read_exact(&mut id)?;
+    Ok((id, read_u64(reader)?))
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> Result<u16> {
+    let mut bytes = [0u8; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Reads `len` bytes of UTF-16LE, dropping the null terminator.
+fn read_utf16<R: Read>(reader: &mut R, len: u16) -> Result<String> {
+    let mut bytes = vec![0u8; usize::from(len)];
+    reader.read_exact(&mut bytes)?;
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    Ok(String::from_utf16_lossy(&units).trim_end_matches('\0').to_owned())
+}
@@ -0,0 +1,37 @@
+#[cfg(test)]
+mod asf_header_tests {
+    use std::io::Cursor;
+
+    use crate::metadata::asf_header::AsfHeader;
+
+    /// The header object GUID of every ASF file.
+    const HEADER_OBJECT: [u8; 16] = [
+        0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11, 0xA6, 0xD9, 0x00, 0xAA, 0x00, 0x62, 0xCE, 0x6C,
+    ];
+
+    /// A header object announcing `count` objects, followed by `objects`.
+    fn asf(count: u32, objects: &[u8]) -> Vec<u8> {
+        let mut bytes = HEADER_OBJECT.to_vec();
+        bytes.extend(((30 + objects.len()) as u64).to_le_bytes());
+        bytes.extend(count.to_le_bytes());
+        bytes.extend([1, 2]);
+        bytes.extend(objects);
+        bytes
+    }
+
+    #[test]
+    fn test_skips_unknown_objects() {
+        let mut object = [0xAB; 16].to_vec();
+        object.extend(24u64.to_le_bytes());
+        assert_eq!(AsfHeader::read_from(Cursor::new(asf(1, &object))).unwrap(), AsfHeader::default());
+    }
+
+    #[test]
+    fn test_truncated_and_oversized_objects_are_errors() {
+        assert!(AsfHeader::read_from(Cursor::new(asf(1, &[0xAB; 10]))).is_err());
+
+        let mut object = [0xAB; 16].to_vec();
+        object.extend((u64::MAX - 8).to_le_bytes());
+        assert!(AsfHeader::read_from(Cursor::new(asf(1, &object))).is_err());
+    }
+}
@@ -1,54 +1,182 @@
 use std::fs::File;
+use std::path::Path;
 
-use audiotags::Tag;
-use log::{error, warn};
+use audiotags::{Tag, TagType};
+use id3::TagLike;
+use log::{debug, error, info, warn};
 use symphonia::core::errors::Error;
 use symphonia::core::errors::Error::DecodeError;
-use symphonia::core::formats::FormatOptions;
+use symphonia::core::formats::{FormatOptions, FormatReader, TrackType};
 use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
+use symphonia::core::meta::{MetadataOptions, StandardTag};
 use symphonia::core::units::Timestamp;
 use symphonia::default::get_probe;
 
-use crate::model::data_model::{Song, SongMetadata};
+use crate::metadata::asf_header::AsfHeader;
+use crate::metadata::path_pattern::PathPattern;
+use crate::metadata::riff_info::RiffInfo;
+use crate::model::data_model::{AudioExtensions, FieldSource, FieldSources, Song, SongMetadata};
 
 #[derive(Debug)]
 pub struct MetadataExtractor {}
 
+/// The tags a lookup needs, whichever library read them.
+#[derive(Debug, Default)]
+struct SongTags {
+    title: String,
+    artist: String,
+    album_title: String,
+}
+
 impl MetadataExtractor {
     pub fn extract(song: &Song) -> Option<SongMetadata> {
-        let borrowed_song = song.to_owned();
-        let borrowed_song2 = song.to_owned();
-        let tag = Tag::new().read_from_path(borrowed_song.filepath);
-        if tag.is_err() {
-            error!("Empty or invalid tags for {}", &borrowed_song.filename);
-            return None;
+        Self::extract_with_patterns(song, &PathPattern::defaults())
+    }
+
+    /// Reads the tags, then fills whatever they lack from the first of
+    /// `patterns` that matches the song's path.
+    pub fn extract_with_patterns(song: &Song, patterns: &[PathPattern]) -> Option<SongMetadata> {
+        let tags = Self::read_tags(song).unwrap_or_else(|| {
+            warn!("Empty or invalid tags for {}", &song.filename);
+            SongTags::default()
+        });
+        let SongTags { mut title, mut artist, mut album_title } = tags;
+        let mut sources = FieldSources::default();
+
+        if title.is_empty() || artist.is_empty() || album_title.is_empty() {
+            let matched = patterns
+                .iter()
+                .find_map(|pattern| pattern.matches(&song.filepath).map(|fields| (pattern, fields)));
+            if let Some((pattern, fields)) = matched {
+                debug!("Path pattern '{}' matched {}", pattern, song.filepath.display());
+                Self::fill(&mut artist, &mut sources.artist, fields.artist);
+                Self::fill(&mut title, &mut sources.title, fields.title);
+                Self::fill(&mut album_title, &mut sources.album_title, fields.album_title);
+            }
+        }
+        for (value, source) in [
+            (&artist, &mut sources.artist),
+            (&title, &mut sources.title),
+            (&album_title, &mut sources.album_title),
+        ] {
+            if value.is_empty() {
+                *source = FieldSource::Missing;
+            }
         }
-        let tag = tag.unwrap();
-        let title = String::from(tag.title().unwrap_or(""));
-        let artist = String::from(tag.artist().unwrap_or(""));
-        let album_title = String::from(tag.album_title().unwrap_or(""));
+
         if title.is_empty() && artist.is_empty() {
-            warn!("Artist or title is empty. Skipping song.");
+            error!("Artist or title is empty in tags and path of {}. Skipping song.", &song.filename);
             return None;
         }
-        let duration = Self::get_duration(borrowed_song2).map_or(None, |seconds| Some(seconds));
-        Some(SongMetadata { song: song.to_owned(), artist, title, album_title, duration })
+        if !sources.all_tags() {
+            info!("Metadata of {}: {}", &song.filename, sources);
+        }
+        let duration = Self::duration(song);
+        Some(SongMetadata { song: song.to_owned(), artist, title, album_title, duration, sources })
+    }
+
+    /// Track length in seconds, when the container reports one.
+    pub fn duration(song: &Song) -> Option<f64> {
+        Self::get_duration(song).ok()
     }
-    fn get_duration(song: Song) -> Result<u16, Error> {
-        let file = File::open(song.filepath)?;
+
+    fn fill(field: &mut String, source: &mut FieldSource, value: Option<String>) {
+        if let Some(value) = value.filter(|_| field.is_empty()) {
+            *field = value;
+            *source = FieldSource::Path;
+        }
+    }
+
+    /// `audiotags` covers MP3, FLAC and MP4; symphonia reads the tags of the
+    /// other formats it demuxes. WAV `INFO` chunks and ASF headers are parsed
+    /// by hand.
+    fn read_tags(song: &Song) -> Option<SongTags> {
+        match song.extension {
+            AudioExtensions::MP3 | AudioExtensions::FLAC | AudioExtensions::M4A => {
+                // The detected format wins over whatever the extension claims.
+                let tag_type = match song.extension {
+                    AudioExtensions::MP3 => TagType::Id3v2,
+                    AudioExtensions::FLAC => TagType::Flac,
+                    _ => TagType::Mp4,
+                };
+                let tag = Tag::new().with_tag_type(tag_type).read_from_path(&song.filepath).ok()?;
+                Some(SongTags {
+                    title: String::from(tag.title().unwrap_or("")),
+                    artist: String::from(tag.artist().unwrap_or("")),
+                    album_title: String::from(tag.album_title().unwrap_or("")),
+                })
+            }
+            AudioExtensions::WMA => {
+                let header = AsfHeader::read(&song.filepath).ok()?;
+                Some(SongTags { title: header.title, artist: header.artist, album_title: header.album_title })
+            }
+            AudioExtensions::WAV => {
+                let info = RiffInfo::read(&song.filepath).ok()?;
+                if !info.title.is_empty() || !info.artist.is_empty() {
+                    return Some(SongTags { title: info.title, artist: info.artist, album_title: info.album_title });
+                }
+                let tag = id3::Tag::read_from_path(&song.filepath).ok()?;
+                Some(SongTags {
+                    title: String::from(tag.title().unwrap_or("")),
+                    artist: String::from(tag.artist().unwrap_or("")),
+                    album_title: String::from(tag.album().unwrap_or("")),
+                })
+            }
+            _ => Self::read_symphonia_tags(&song.filepath).ok(),
+        }
+    }
+
+    fn read_symphonia_tags(path: &Path) -> Result<SongTags, Error> {
+        let mut reader = Self::probe(path)?;
+        let mut metadata = reader.metadata();
+        let mut tags = SongTags::default();
+        loop {
+            if let Some(revision) = metadata.current() {
+                let per_track = revision.per_track.iter().map(|track| &track.metadata);
+                for tag in std::iter::once(&revision.media).chain(per_track).flat_map(|m| &m.tags) {
+                    match &tag.std {
+                        Some(StandardTag::TrackTitle(value)) => tags.title = value.to_string(),
+                        Some(StandardTag::Artist(value)) => tags.artist = value.to_string(),
+                        Some(StandardTag::AlbumArtist(value)) if tags.artist.is_empty() => {
+                            tags.artist = value.to_string()
+                        }
+                        Some(StandardTag::Album(value)) => tags.album_title = value.to_string(),
+                        _ => {}
+                    }
+                }
+            }
+            if metadata.is_latest() {
+                break;
+            }
+            metadata.pop();
+        }
+        Ok(tags)
+    }
+
+    fn probe(path: &Path) -> Result<Box<dyn FormatReader>, Error> {
+        let file = File::open(path)?;
         let mss = MediaSourceStream::new(Box::new(file), Default::default());
-        let probed = get_probe().probe(
+        get_probe().probe(
             &Default::default(),
             mss,
             FormatOptions::default(),
             MetadataOptions::default(),
-        )?;
+        )
+    }
+
+    fn get_duration(song: &Song) -> Result<f64, Error> {
+        if song.extension == AudioExtensions::WMA {
+            return AsfHeader::read(&song.filepath)
+                .ok()
+                .and_then(|header| header.duration)
+                .ok_or(DecodeError("Could not get the seconds"));
+        }
+        let probed = Self::probe(&song.filepath)?;
         let track = probed
-            .tracks()
-            .first()
+            .default_track(TrackType::Audio)
+            .or_else(|| probed.tracks().first())
             .ok_or(DecodeError("No audio tracks found"))?;
-        
+
         let time_base = track
             .time_base
             .ok_or(DecodeError("Track timebase missing"))?;
@@ -56,7 +184,7 @@ impl MetadataExtractor {
         let seconds = track
             .duration
             .and_then(|dur| time_base.calc_time(Timestamp::new(dur.get() as i64)))
-            .map(|time| time.as_secs() as u16)
+            .map(|time| time.as_secs_f64())
             .ok_or(DecodeError("Could not get the seconds"))?;
         Ok(seconds)
     }
@@ -1,10 +1,12 @@
 #[cfg(test)]
 mod metadata_extractor_tests {
-    use std::env;
+    use std::{env, fs};
     use crate::metadata::metadata_extractor::MetadataExtractor;
-    use crate::model::data_model::Song;
+    use crate::metadata::path_pattern::PathPattern;
+    use crate::model::data_model::{FieldSource, Song};
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_metadata() {
         let current_dir = env::current_dir().unwrap();
         let extensions = vec!["mp3"];
@@ -21,8 +23,87 @@ mod metadata_extractor_tests {
             let metadata = metadata.unwrap();
             assert_eq!("Benny Blanco", metadata.artist);
             assert_eq!("Roses", metadata.title);
-            assert_eq!(Some(223), metadata.duration)
+            let duration = metadata.duration.unwrap();
+            assert!((duration - 223.634).abs() < 0.01, "duration {}", duration);
         }
 
     }
+
+    #[test]
+    fn test_metadata_of_every_format() {
+        let current_dir = env::current_dir().unwrap();
+        let fixtures = vec![
+            ("ogg", 5.627),
+            ("opus", 3.0),
+            ("m4a", 0.485),
+            ("aac", 3.019),
+            ("wav", 3.0),
+            ("aiff", 3.0),
+            ("wma", 3.0),
+        ];
+        for (ext, duration) in fixtures {
+            let binding = current_dir.join(format!("test_resources/fixture.{}", ext));
+            let song = Song::new(binding.to_str().unwrap()).unwrap();
+            assert!(song.is_file(), "File {} does not exist", binding.display());
+            let metadata = MetadataExtractor::extract(&song);
+            assert!(metadata.is_some(), "No metadata for {}", ext);
+            let metadata = metadata.unwrap();
+            assert_eq!("GetLyrics", metadata.artist, "artist of {}", ext);
+            assert_eq!("Fixture", metadata.title, "title of {}", ext);
+            assert_eq!("Fixtures", metadata.album_title, "album of {}", ext);
+            let actual = metadata.duration.unwrap();
+            assert!((actual - duration).abs() < 0.01, "duration of {}: {}", ext, actual);
+        }
+    }
+
+    #[test]
+    fn test_untagged_song_falls_back_to_path() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let album = tmp_dir.path().join("Benny Blanco").join("Friends");
+        fs::create_dir_all(&album).unwrap();
+        let path = album.join("03 - Roses.mp3");
+        fs::copy("test_resources/benny_blanco-roses.mp3", &path).unwrap();
+        id3::Tag::remove_from_path(&path).unwrap();
+
+        let song = Song::new(path.to_str().unwrap()).unwrap();
+        let metadata = MetadataExtractor::extract(&song).unwrap();
+        assert_eq!("Benny Blanco", metadata.artist);
+        assert_eq!("Roses", metadata.title);
+        assert_eq!("Friends", metadata.album_title);
+        assert_eq!(FieldSource::Path, metadata.sources.artist);
+        assert_eq!(FieldSource::Path, metadata.sources.title);
+        assert_eq!(FieldSource::Path, metadata.sources.album_title);
+    }
+
+    #[test]
+    fn test_tags_win_over_path() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("Someone Else - Another Song.mp3");
+        fs::copy("test_resources/benny_blanco-roses.mp3", &path).unwrap();
+
+        let song = Song::new(path.to_str().unwrap()).unwrap();
+        let metadata = MetadataExtractor::extract(&song).unwrap();
+        assert_eq!("Benny Blanco", metadata.artist);
+        assert_eq!("Roses", metadata.title);
+        assert_eq!(FieldSource::Tag, metadata.sources.artist);
+        assert_eq!(FieldSource::Tag, metadata.sources.title);
+    }
+
+    #[test]
+    fn test_custom_pattern_and_missing_fields() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("Roses by Benny Blanco.mp3");
+        fs::copy("test_resources/benny_blanco-roses.mp3", &path).unwrap();
+        id3::Tag::remove_from_path(&path).unwrap();
+        let song = Song::new(path.to_str().unwrap()).unwrap();
+
+        assert!(MetadataExtractor::extract(&song).is_none());
+
+        let patterns = vec![PathPattern::parse("{title} by {artist}").unwrap()];
+        let metadata = MetadataExtractor::extract_with_patterns(&song, &patterns).unwrap();
+        assert_eq!("Benny Blanco", metadata.artist);
+        assert_eq!("Roses", metadata.title);
+        assert_eq!(FieldSource::Path, metadata.sources.title);
+        assert_eq!(FieldSource::Missing, metadata.sources.album_title);
+    }
 }
\ No newline at end of file
@@ -1,2 +1,8 @@
 pub mod metadata_extractor;
-mod metadata_extractor_test;
\ No newline at end of file
+pub mod asf_header;
+pub mod path_pattern;
+pub mod riff_info;
+mod asf_header_test;
+mod metadata_extractor_test;
+mod path_pattern_test;
+mod riff_info_test;
\ No newline at end of file
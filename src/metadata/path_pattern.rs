@@ -0,0 +1,132 @@
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+
+use regex::Regex;
+
+/// Fields a [`PathPattern`] recovered from a path.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct PathFields {
+    pub artist: Option<String>,
+    pub album_title: Option<String>,
+    pub title: Option<String>,
+    pub track: Option<u32>,
+}
+
+/// A template such as `{artist}/{album}/{track} - {title}` matched against
+/// the last folders of a path and the file name without its extension.
+///
+/// Placeholders are `{artist}`, `{album}`, `{title}`, `{track}` (digits) and
+/// `{any}`, which matches anything and is discarded.
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    pattern: String,
+    segments: Vec<Regex>,
+}
+
+impl PathPattern {
+    /// Tried in order when no pattern is configured, most specific first.
+    pub const DEFAULTS: [&'static str; 4] = [
+        "{artist}/{album}/{track} - {title}",
+        "{artist}/{album}/{track}. {title}",
+        "{track} - {artist} - {title}",
+        "{artist} - {title}",
+    ];
+
+    pub fn defaults() -> Vec<PathPattern> {
+        Self::DEFAULTS
+            .iter()
+            .map(|pattern| Self::parse(pattern).expect("default path patterns are valid"))
+            .collect()
+    }
+
+    pub fn parse(pattern: &str) -> Result<PathPattern, String> {
+        let mut seen: Vec<&str> = Vec::new();
+        let mut segments = Vec::new();
+        for segment in pattern.trim_matches('/').split('/') {
+            let mut regex = String::from("^");
+            let mut rest = segment;
+            while let Some(start) = rest.find('{') {
+                regex.push_str(&regex::escape(&rest[..start]));
+                let end = rest[start..]
+                    .find('}')
+                    .ok_or_else(|| format!("Unclosed placeholder in path pattern: {}", pattern))?
+                    + start;
+                let name = &rest[start + 1..end];
+                match name {
+                    "artist" | "album" | "title" | "track" => {
+                        if seen.contains(&name) {
+                            return Err(format!("{{{}}} appears twice in path pattern: {}", name, pattern));
+                        }
+                        seen.push(name);
+                        let body = if name == "track" { r"\d+" } else { ".+?" };
+                        regex.push_str(&format!("(?P<{}>{})", name, body));
+                    }
+                    "any" => regex.push_str(".*?"),
+                    _ => return Err(format!("Unknown placeholder {{{}}} in path pattern: {}", name, pattern)),
+                }
+                rest = &rest[end + 1..];
+            }
+            regex.push_str(&regex::escape(rest));
+            regex.push('$');
+            segments.push(Regex::new(&regex).map_err(|e| e.to_string())?);
+        }
+        if !seen.contains(&"artist") && !seen.contains(&"title") {
+            return Err(format!("Path pattern needs {{artist}} or {{title}}: {}", pattern));
+        }
+        Ok(PathPattern {
+            pattern: pattern.to_owned(),
+            segments,
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// The fields of `path` when every segment of the pattern matches.
+    pub fn matches(&self, path: &Path) -> Option<PathFields> {
+        let stem = path.file_stem()?.to_str()?;
+        let mut components: Vec<&str> = path
+            .parent()
+            .into_iter()
+            .flat_map(|parent| parent.iter())
+            .filter_map(|component| component.to_str())
+            .collect();
+        components.push(stem);
+        if components.len() < self.segments.len() {
+            return None;
+        }
+
+        let mut fields = PathFields::default();
+        let components = &components[components.len() - self.segments.len()..];
+        for (regex, component) in self.segments.iter().zip(components) {
+            let captures = regex.captures(component)?;
+            let text = |name: &str| {
+                captures
+                    .name(name)
+                    .map(|value| value.as_str().trim().to_owned())
+                    .filter(|value| !value.is_empty())
+            };
+            fields.artist = fields.artist.or_else(|| text("artist"));
+            fields.album_title = fields.album_title.or_else(|| text("album"));
+            fields.title = fields.title.or_else(|| text("title"));
+            fields.track = fields.track.or_else(|| text("track").and_then(|track| track.parse().ok()));
+        }
+        Some(fields)
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = String;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        Self::parse(pattern)
+    }
+}
+
+impl Display for PathPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod path_pattern_tests {
+    use std::path::Path;
+
+    use crate::metadata::path_pattern::{PathFields, PathPattern};
+
+    #[test]
+    fn test_folder_pattern() {
+        let pattern = PathPattern::parse("{artist}/{album}/{track} - {title}").unwrap();
+        let fields = pattern.matches(Path::new("/music/Benny Blanco/Friends/03 - Roses.mp3")).unwrap();
+        assert_eq!(
+            fields,
+            PathFields {
+                artist: Some(String::from("Benny Blanco")),
+                album_title: Some(String::from("Friends")),
+                title: Some(String::from("Roses")),
+                track: Some(3),
+            }
+        );
+    }
+
+    #[test]
+    fn test_filename_pattern() {
+        let pattern = PathPattern::parse("{artist} - {title}").unwrap();
+        let fields = pattern.matches(Path::new("downloads/Benny Blanco - Roses (Remix).flac")).unwrap();
+        assert_eq!(fields.artist.as_deref(), Some("Benny Blanco"));
+        assert_eq!(fields.title.as_deref(), Some("Roses (Remix)"));
+        assert_eq!(fields.album_title, None);
+    }
+
+    #[test]
+    fn test_no_match() {
+        let pattern = PathPattern::parse("{artist}/{album}/{track} - {title}").unwrap();
+        assert_eq!(pattern.matches(Path::new("Roses.mp3")), None);
+        assert_eq!(pattern.matches(Path::new("a/b/Roses.mp3")), None);
+        let pattern = PathPattern::parse("{artist} - {title}").unwrap();
+        assert_eq!(pattern.matches(Path::new("Roses.mp3")), None);
+    }
+
+    #[test]
+    fn test_literals_and_any() {
+        let pattern = PathPattern::parse("{artist}/[{any}] {album}/{title}").unwrap();
+        let fields = pattern.matches(Path::new("Artist/[2019] Album.v2/Song.ogg")).unwrap();
+        assert_eq!(fields.artist.as_deref(), Some("Artist"));
+        assert_eq!(fields.album_title.as_deref(), Some("Album.v2"));
+        assert_eq!(fields.title.as_deref(), Some("Song"));
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert!(PathPattern::parse("{artist} - {name}").is_err());
+        assert!(PathPattern::parse("{artist} - {title").is_err());
+        assert!(PathPattern::parse("{artist}/{artist} - {title}").is_err());
+        assert!(PathPattern::parse("{album}/{track}").is_err());
+        assert!("{artist} - {title}".parse::<PathPattern>().is_ok());
+    }
+
+    #[test]
+    fn test_defaults_are_tried_in_order() {
+        let path = Path::new("Artist/Album/01 - Title.mp3");
+        let first = PathPattern::defaults().into_iter().find_map(|p| p.matches(path)).unwrap();
+        assert_eq!(first.album_title.as_deref(), Some("Album"));
+        assert_eq!(first.artist.as_deref(), Some("Artist"));
+        assert_eq!(first.title.as_deref(), Some("Title"));
+    }
+}
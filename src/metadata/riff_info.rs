@@ -0,0 +1,76 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+/// Tags from the `LIST`/`INFO` chunk of a WAV file. symphonia parses it but
+/// does not hand the result back to callers.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RiffInfo {
+    pub title: String,
+    pub artist: String,
+    pub album_title: String,
+}
+
+impl RiffInfo {
+    pub fn read(path: &Path) -> Result<RiffInfo> {
+        let file = File::open(path).with_context(|| format!("Failed to open file: {:?}", path))?;
+        Self::read_from(BufReader::new(file))
+    }
+
+    pub fn read_from<R: Read + Seek>(mut reader: R) -> Result<RiffInfo> {
+        let (id, _) = read_chunk_header(&mut reader)?;
+        if &id != b"RIFF" || &read_id(&mut reader)? != b"WAVE" {
+            bail!("Not a WAV file");
+        }
+
+        let mut info = RiffInfo::default();
+        while let Ok((id, len)) = read_chunk_header(&mut reader) {
+            let start = reader.stream_position()?;
+            if &id == b"LIST" && &read_id(&mut reader)? == b"INFO" {
+                info.read_info_list(&mut reader, start + u64::from(len))?;
+            }
+            // Chunks are padded to an even length.
+            reader.seek(SeekFrom::Start(start + u64::from(len) + u64::from(len % 2)))?;
+        }
+        Ok(info)
+    }
+
+    fn read_info_list<R: Read + Seek>(&mut self, reader: &mut R, end: u64) -> Result<()> {
+        while reader.stream_position()? + 8 <= end {
+            let (id, len) = read_chunk_header(reader)?;
+            // Don't trust the length further than the list it is in.
+            let position = reader.stream_position()?;
+            if u64::from(len) > end.saturating_sub(position) {
+                bail!("Corrupt INFO chunk length {}", len);
+            }
+            let mut value = vec![0u8; len as usize];
+            reader.read_exact(&mut value)?;
+            if len % 2 == 1 {
+                reader.seek(SeekFrom::Current(1))?;
+            }
+            let value = String::from_utf8_lossy(&value).trim_end_matches('\0').trim().to_owned();
+            match &id {
+                b"INAM" => self.title = value,
+                b"IART" => self.artist = value,
+                b"IPRD" => self.album_title = value,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_id<R: Read>(reader: &mut R) -> Result<[u8; 4]> {
+    let mut id = [0u8; 4];
+    reader.read_exact(&mut id)?;
+    Ok(id)
+}
+
+fn read_chunk_header<R: Read>(reader: &mut R) -> Result<([u8; 4], u32)> {
+    let id = read_id(reader)?;
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    Ok((id, u32::from_le_bytes(len)))
+}
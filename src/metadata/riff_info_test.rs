@@ -0,0 +1,30 @@
+#[cfg(test)]
+mod riff_info_tests {
+    use std::io::Cursor;
+
+    use crate::metadata::riff_info::RiffInfo;
+
+    /// A WAV file whose `LIST`/`INFO` chunk holds one `INAM` sub-chunk.
+    fn wav(list_len: u32, name_len: u32, name: &[u8]) -> Vec<u8> {
+        let mut bytes = b"RIFF\0\0\0\0WAVELIST".to_vec();
+        bytes.extend(list_len.to_le_bytes());
+        bytes.extend(b"INFOINAM");
+        bytes.extend(name_len.to_le_bytes());
+        bytes.extend(name);
+        bytes
+    }
+
+    #[test]
+    fn test_reads_info_title() {
+        let info = RiffInfo::read_from(Cursor::new(wav(18, 6, b"Roses\0"))).unwrap();
+        assert_eq!(info.title, "Roses");
+    }
+
+    #[test]
+    fn test_truncated_and_oversized_lengths_are_errors() {
+        // The list claims more than the file has, and so does the title.
+        assert!(RiffInfo::read_from(Cursor::new(wav(1000, 100, b"Ros"))).is_err());
+        // The title claims 4 GiB inside an 18 byte list.
+        assert!(RiffInfo::read_from(Cursor::new(wav(18, u32::MAX, b"Roses\0"))).is_err());
+    }
+}
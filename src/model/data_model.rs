@@ -5,14 +5,23 @@ use std::path::{Path, PathBuf};
 use log::error;
 
 use crate::model::data_model::AudioExtensions::{
-    AAC, AIFF, FLAC, M4A, MP3, OGG, UNKNOWN, WAV, WMA,
+    AAC, AIFF, FLAC, M4A, MP3, OGG, OPUS, UNKNOWN, WAV, WMA,
 };
+use crate::model::lrc::LrcDocument;
 
 #[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Clone, Hash)]
 pub struct Lyric {
     pub lyric: String,
     pub song: Song,
 }
+impl Lyric {
+    pub fn document(&self) -> LrcDocument {
+        LrcDocument::parse(&self.lyric)
+    }
+    pub fn is_synced(&self) -> bool {
+        self.document().is_synced()
+    }
+}
 #[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Clone, Hash)]
 pub struct Song {
     pub filename: String,
@@ -22,7 +31,7 @@ pub struct Song {
 impl Song {
     pub fn new(filepath: &str) -> Option<Song> {
         let filepath_as_path = filepath.as_ref();
-        let extension = AudioExtensions::get_extension_by_filepath(filepath_as_path);
+        let extension = AudioExtensions::detect(filepath_as_path);
         if extension == UNKNOWN {
             error!("Invalid file/extension on filepath => {}", &filepath);
             return None;
@@ -62,13 +71,52 @@ impl Display for Song {
         )
     }
 }
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SongMetadata {
     pub song: Song,
     pub artist: String,
     pub title: String,
     pub album_title: String,
-    pub duration: Option<u16>,
+    /// Track length in seconds, with sub-second precision.
+    pub duration: Option<f64>,
+    pub sources: FieldSources,
+}
+
+/// Where a [`SongMetadata`] field was read from.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum FieldSource {
+    #[default]
+    Tag,
+    /// Parsed from the file name or folders by a path pattern.
+    Path,
+    Missing,
+}
+impl Display for FieldSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldSource::Tag => write!(f, "tag"),
+            FieldSource::Path => write!(f, "path"),
+            FieldSource::Missing => write!(f, "missing"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone, Hash)]
+pub struct FieldSources {
+    pub artist: FieldSource,
+    pub title: FieldSource,
+    pub album_title: FieldSource,
+}
+impl FieldSources {
+    /// Whether every field came from the tags.
+    pub fn all_tags(&self) -> bool {
+        *self == FieldSources::default()
+    }
+}
+impl Display for FieldSources {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "artist from {}, title from {}, album from {}", self.artist, self.title, self.album_title)
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Copy, Clone, Hash)]
@@ -81,9 +129,13 @@ pub enum AudioExtensions {
     AIFF,
     WMA,
     AAC,
+    OPUS,
     UNKNOWN,
 }
 impl AudioExtensions {
+    /// Every processable format.
+    pub const SUPPORTED: [AudioExtensions; 9] = [MP3, OGG, M4A, FLAC, WAV, AIFF, WMA, AAC, OPUS];
+
     pub(crate) fn get_extension(&self) -> &'static str {
         match &self {
             MP3 => "mp3",
@@ -94,9 +146,19 @@ impl AudioExtensions {
             AIFF => "aiff",
             WMA => "wma",
             AAC => "aac",
+            OPUS => "opus",
             UNKNOWN => "unknown",
         }
     }
+    /// Other spellings of the extension found in the wild.
+    fn get_aliases(&self) -> &'static [&'static str] {
+        match &self {
+            OGG => &["oga"],
+            M4A => &["mp4"],
+            AIFF => &["aif"],
+            _ => &[],
+        }
+    }
     fn get_extension_as_str(filepath: &Path) -> String {
         let cloned = filepath.to_owned();
         let os_string = cloned
@@ -106,11 +168,11 @@ impl AudioExtensions {
         String::from(os_string.to_str().unwrap_or(""))
     }
     pub fn get_extension_by_filepath(filepath: &Path) -> AudioExtensions {
-        let extensions = [MP3, OGG, M4A, FLAC, WAV, AIFF, WMA, AAC];
         let current_extension = Self::get_extension_as_str(filepath);
-        let result = extensions
-            .into_iter()
-            .find(|an_extension| current_extension.as_str() == an_extension.get_extension());
+        let result = Self::SUPPORTED.into_iter().find(|an_extension| {
+            current_extension.as_str() == an_extension.get_extension()
+                || an_extension.get_aliases().contains(&current_extension.as_str())
+        });
 
         result.unwrap_or(UNKNOWN)
     }
@@ -122,3 +184,115 @@ impl Display for AudioExtensions {
 }
 
 pub struct Writer;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WriterOptions {
+    /// Prepend `[ar:]`, `[ti:]`, `[al:]`, `[length:]` and `[re:]` tags.
+    pub id_tags: bool,
+    /// Write a `.lrc` file next to the song.
+    pub sidecar: bool,
+    /// Store the lyric inside the song's own tags.
+    pub embed: bool,
+    /// Subtitle files written next to the song for synced lyrics.
+    pub subtitles: Vec<SubtitleFormat>,
+    /// Add approximate enhanced LRC word times to synced lines that have none.
+    pub word_timings: bool,
+    /// What happens to a `.lrc` file that already exists.
+    pub overwrite: OverwritePolicy,
+}
+impl Default for WriterOptions {
+    fn default() -> Self {
+        WriterOptions {
+            id_tags: true,
+            sidecar: true,
+            embed: false,
+            subtitles: Vec::new(),
+            word_timings: false,
+            overwrite: OverwritePolicy::default(),
+        }
+    }
+}
+
+/// Whether an existing `.lrc` file next to the song is replaced.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum OverwritePolicy {
+    #[default]
+    Always,
+    /// Keep every existing file, e.g. lyrics edited by hand.
+    Never,
+    /// Only replace files without synced lines, and only with synced lyrics.
+    Upgrade,
+}
+impl OverwritePolicy {
+    pub fn from_name(name: &str) -> Option<OverwritePolicy> {
+        match name.trim().to_lowercase().as_str() {
+            "always" => Some(OverwritePolicy::Always),
+            "never" => Some(OverwritePolicy::Never),
+            "upgrade" => Some(OverwritePolicy::Upgrade),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OverwritePolicy::Always => "always",
+            OverwritePolicy::Never => "never",
+            OverwritePolicy::Upgrade => "upgrade",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SubtitleFormat {
+    Srt,
+    WebVtt,
+    Ttml,
+}
+impl SubtitleFormat {
+    pub const ALL: [SubtitleFormat; 3] = [SubtitleFormat::Srt, SubtitleFormat::WebVtt, SubtitleFormat::Ttml];
+
+    pub fn from_name(name: &str) -> Option<SubtitleFormat> {
+        match name.trim().to_lowercase().as_str() {
+            "srt" => Some(SubtitleFormat::Srt),
+            "vtt" | "webvtt" => Some(SubtitleFormat::WebVtt),
+            "ttml" => Some(SubtitleFormat::Ttml),
+            _ => None,
+        }
+    }
+
+    /// Parses a comma separated list such as `srt,vtt`.
+    pub fn parse_list(names: &str) -> Result<Vec<SubtitleFormat>, String> {
+        names
+            .split(',')
+            .filter(|name| !name.trim().is_empty())
+            .map(|name| Self::from_name(name).ok_or_else(|| format!("Unknown subtitle format: {}", name.trim())))
+            .collect()
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::WebVtt => "vtt",
+            SubtitleFormat::Ttml => "ttml",
+        }
+    }
+}
+
+/// How `shift` moves an existing `.lrc` file.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum ShiftMode {
+    /// Rewrite every timestamp.
+    #[default]
+    Timestamps,
+    /// Leave the timestamps alone and adjust the `[offset:]` tag.
+    OffsetTag,
+}
+impl ShiftMode {
+    pub fn from_name(name: &str) -> Option<ShiftMode> {
+        match name.trim().to_lowercase().as_str() {
+            "timestamps" => Some(ShiftMode::Timestamps),
+            "tag" | "offset" => Some(ShiftMode::OffsetTag),
+            _ => None,
+        }
+    }
+}
@@ -2,7 +2,7 @@
 #[cfg(test)]
 mod data_model_tests {
     use std::path::PathBuf;
-    use crate::model::data_model::{AudioExtensions, Song};
+    use crate::model::data_model::{AudioExtensions, OverwritePolicy, Song};
 
     #[test]
     fn test_audio_extensions_get_extension() {
@@ -24,6 +24,17 @@ mod data_model_tests {
     }
 
     #[test]
+    fn test_audio_extensions_every_supported_format() {
+        for extension in AudioExtensions::SUPPORTED {
+            let path = PathBuf::from(format!("song.{}", extension.get_extension().to_uppercase()));
+            assert_eq!(AudioExtensions::get_extension_by_filepath(&path), extension);
+        }
+        assert_eq!(AudioExtensions::get_extension_by_filepath(&PathBuf::from("a.opus")), AudioExtensions::OPUS);
+        assert_eq!(AudioExtensions::get_extension_by_filepath(&PathBuf::from("a.aif")), AudioExtensions::AIFF);
+    }
+
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_song_new_valid_extension() {
         let song = Song::new("test.mp3");
         assert!(song.is_some());
@@ -59,4 +70,11 @@ mod data_model_tests {
         assert_eq!(format!("{}", AudioExtensions::MP3), "mp3");
         assert_eq!(format!("{}", AudioExtensions::UNKNOWN), "unknown");
     }
+
+    #[test]
+    fn test_overwrite_policy_from_name() {
+        assert_eq!(OverwritePolicy::from_name("Upgrade"), Some(OverwritePolicy::Upgrade));
+        assert_eq!(OverwritePolicy::from_name(" never "), Some(OverwritePolicy::Never));
+        assert_eq!(OverwritePolicy::from_name("sometimes"), None);
+    }
 }
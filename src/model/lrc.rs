@@ -0,0 +1,535 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use regex::Regex;
+
+/// An inline enhanced LRC time such as `<00:12.34>`.
+static WORD_TIME: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<([^<>]+)>").unwrap());
+/// The inside of a timestamp: `mm:ss`, `mm:ss.xx` or `mm:ss:xx`.
+static TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\d+):(\d{2})(?:([.:])(\d{1,3}))?$").unwrap());
+/// An ID tag line such as `[ar:Artist]`.
+static ID_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\[([A-Za-z#][A-Za-z0-9#_-]*):(.*)\]$").unwrap());
+
+/// A parsed `.lrc` lyric.
+///
+/// Parsing never fails and is lossless: `LrcDocument::parse(text).to_string() == text`
+/// for any input. Lines that are not ID tags or well-formed timed lines are kept
+/// verbatim as [`LrcLine::Untimed`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Default)]
+pub struct LrcDocument {
+    pub lines: Vec<LrcLine>,
+    crlf: bool,
+    trailing_newline: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum LrcLine {
+    Tag(IdTag),
+    Timed(TimedLine),
+    Untimed(String),
+}
+
+/// An `[key:value]` header line. The value is stored exactly as written.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct IdTag {
+    pub key: String,
+    pub value: String,
+}
+
+/// A lyric line with one or more leading timestamps, e.g. `[00:12.00][01:30.00]Chorus`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TimedLine {
+    pub timestamps: Vec<LrcTimestamp>,
+    pub text: String,
+}
+
+/// A word of an enhanced LRC (A2) line: the `<mm:ss.xx>` time it starts at and
+/// the text up to the next time. A last time with no text marks where the line ends.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TimedWord {
+    pub timestamp: LrcTimestamp,
+    pub text: String,
+}
+
+/// A `[mm:ss.xx]` timestamp. The original layout (minute padding, number of
+/// fraction digits and separator) is kept so serialization reproduces it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct LrcTimestamp {
+    pub millis: u64,
+    minute_digits: u8,
+    fraction_digits: u8,
+    separator: char,
+}
+
+impl LrcTimestamp {
+    /// A timestamp in the common `[mm:ss.xx]` layout.
+    pub fn from_millis(millis: u64) -> Self {
+        LrcTimestamp {
+            millis,
+            minute_digits: 2,
+            fraction_digits: 2,
+            separator: '.',
+        }
+    }
+
+    pub fn as_duration(&self) -> Duration {
+        Duration::from_millis(self.millis)
+    }
+
+    /// Same layout, different time.
+    pub fn with_millis(&self, millis: u64) -> Self {
+        LrcTimestamp { millis, ..*self }
+    }
+
+    /// Moved by `millis`, clamped at zero.
+    pub fn shifted(&self, millis: i64) -> Self {
+        self.with_millis((self.millis as i64).saturating_add(millis).max(0) as u64)
+    }
+
+    /// The `<mm:ss.xx>` form used for word times.
+    pub fn to_inline(&self) -> String {
+        let bracketed = self.to_string();
+        format!("<{}>", &bracketed[1..bracketed.len() - 1])
+    }
+
+    fn parse(raw: &str) -> Option<LrcTimestamp> {
+        let captures = TIMESTAMP.captures(raw)?;
+        let minutes_raw = captures.get(1)?.as_str();
+        let minutes: u64 = minutes_raw.parse().ok()?;
+        let seconds: u64 = captures.get(2)?.as_str().parse().ok()?;
+        if seconds >= 60 {
+            return None;
+        }
+        let (separator, fraction_digits, fraction_millis) = match (captures.get(3), captures.get(4)) {
+            (Some(separator), Some(fraction)) => {
+                let digits = fraction.as_str().len() as u32;
+                let value: u64 = fraction.as_str().parse().ok()?;
+                let separator = separator.as_str().chars().next()?;
+                (separator, digits as u8, value * 10u64.pow(3 - digits))
+            }
+            _ => ('.', 0, 0),
+        };
+        // A timestamp too large to hold is treated as text, like any other malformed one.
+        let millis = minutes
+            .checked_mul(60)
+            .and_then(|total| total.checked_add(seconds))
+            .and_then(|total| total.checked_mul(1000))
+            .and_then(|total| total.checked_add(fraction_millis))?;
+        Some(LrcTimestamp {
+            millis,
+            minute_digits: u8::try_from(minutes_raw.len()).ok()?,
+            fraction_digits,
+            separator,
+        })
+    }
+}
+
+impl Display for LrcTimestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Round to the precision the timestamp was written with.
+        let unit = 10u64.pow(3 - self.fraction_digits as u32);
+        let rounded = self.millis.saturating_add(unit / 2) / unit * unit;
+        let minutes = rounded / 60_000;
+        let seconds = rounded / 1000 % 60;
+        write!(f, "[{:0width$}:{:02}", minutes, seconds, width = self.minute_digits as usize)?;
+        if self.fraction_digits > 0 {
+            let fraction = rounded % 1000 / unit;
+            write!(
+                f,
+                "{}{:0width$}",
+                self.separator,
+                fraction,
+                width = self.fraction_digits as usize
+            )?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl Display for LrcLine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LrcLine::Tag(tag) => write!(f, "[{}:{}]", tag.key, tag.value),
+            LrcLine::Timed(line) => {
+                for timestamp in &line.timestamps {
+                    write!(f, "{}", timestamp)?;
+                }
+                write!(f, "{}", line.text)
+            }
+            LrcLine::Untimed(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+impl TimedLine {
+    /// The inline word times of an enhanced LRC line, empty for line-level lyrics.
+    /// Text before the first time is not part of any word.
+    pub fn words(&self) -> Vec<TimedWord> {
+        let mut words: Vec<TimedWord> = Vec::new();
+        let mut last_end = 0;
+        for captures in WORD_TIME.captures_iter(&self.text) {
+            let Some(timestamp) = LrcTimestamp::parse(&captures[1]) else {
+                continue;
+            };
+            let tag = captures.get(0).unwrap();
+            if let Some(previous) = words.last_mut() {
+                previous.text.push_str(&self.text[last_end..tag.start()]);
+            }
+            words.push(TimedWord { timestamp, text: String::new() });
+            last_end = tag.end();
+        }
+        if let Some(previous) = words.last_mut() {
+            previous.text.push_str(&self.text[last_end..]);
+        }
+        words
+    }
+
+    pub fn has_word_timings(&self) -> bool {
+        WORD_TIME.captures_iter(&self.text).any(|captures| LrcTimestamp::parse(&captures[1]).is_some())
+    }
+
+    /// The text without inline word times.
+    pub fn lyric(&self) -> String {
+        WORD_TIME
+            .replace_all(&self.text, |captures: &regex::Captures| match LrcTimestamp::parse(&captures[1]) {
+                Some(_) => String::new(),
+                None => captures[0].to_owned(),
+            })
+            .trim()
+            .to_owned()
+    }
+
+    pub fn set_words(&mut self, words: &[TimedWord]) {
+        self.text = words
+            .iter()
+            .map(|word| format!("{}{}", word.timestamp.to_inline(), word.text))
+            .collect();
+    }
+
+    fn shift_words(&mut self, millis: i64) {
+        if !self.has_word_timings() {
+            return;
+        }
+        self.text = WORD_TIME
+            .replace_all(&self.text, |captures: &regex::Captures| match LrcTimestamp::parse(&captures[1]) {
+                Some(timestamp) => timestamp.shifted(millis).to_inline(),
+                None => captures[0].to_owned(),
+            })
+            .into_owned();
+    }
+
+    /// Spreads `start..end` over the words of `text`, longer words getting
+    /// more time, and closes the line with the end time.
+    fn synthesize_words(text: &str, start: LrcTimestamp, end: u64) -> Vec<TimedWord> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let total_chars: usize = words.iter().map(|word| word.chars().count()).sum();
+        let span = end.saturating_sub(start.millis);
+        let mut elapsed_chars = 0;
+        let mut timed: Vec<TimedWord> = words
+            .iter()
+            .enumerate()
+            .map(|(index, word)| {
+                let at = start.millis + span * elapsed_chars as u64 / total_chars.max(1) as u64;
+                elapsed_chars += word.chars().count();
+                let separator = if index + 1 < words.len() { " " } else { "" };
+                TimedWord { timestamp: start.with_millis(at), text: format!("{}{}", word, separator) }
+            })
+            .collect();
+        timed.push(TimedWord { timestamp: start.with_millis(end), text: String::new() });
+        timed
+    }
+}
+
+impl LrcLine {
+    fn parse(raw: &str) -> LrcLine {
+        let parsed = Self::parse_tag(raw).or_else(|| Self::parse_timed(raw));
+        match parsed {
+            // Anything that would not serialize back identically stays verbatim.
+            Some(line) if line.to_string() == raw => line,
+            _ => LrcLine::Untimed(raw.to_owned()),
+        }
+    }
+
+    fn parse_tag(raw: &str) -> Option<LrcLine> {
+        let captures = ID_TAG.captures(raw)?;
+        Some(LrcLine::Tag(IdTag {
+            key: captures.get(1)?.as_str().to_owned(),
+            value: captures.get(2)?.as_str().to_owned(),
+        }))
+    }
+
+    fn parse_timed(raw: &str) -> Option<LrcLine> {
+        let mut timestamps = Vec::new();
+        let mut rest = raw;
+        while let Some(inner) = rest.strip_prefix('[') {
+            let end = inner.find(']')?;
+            let Some(timestamp) = LrcTimestamp::parse(&inner[..end]) else {
+                break;
+            };
+            timestamps.push(timestamp);
+            rest = &inner[end + 1..];
+        }
+        if timestamps.is_empty() {
+            return None;
+        }
+        Some(LrcLine::Timed(TimedLine {
+            timestamps,
+            text: rest.to_owned(),
+        }))
+    }
+}
+
+impl LrcDocument {
+    pub const ARTIST: &'static str = "ar";
+    pub const TITLE: &'static str = "ti";
+    pub const ALBUM: &'static str = "al";
+    pub const LENGTH: &'static str = "length";
+    pub const OFFSET: &'static str = "offset";
+    pub const AUTHOR: &'static str = "by";
+    /// How long the last line lasts when the track length is unknown.
+    pub const LAST_LINE_MILLIS: u64 = 4000;
+    /// Seconds the last line may start after the end of the track.
+    pub const DURATION_TOLERANCE: f64 = 2.0;
+
+    pub fn parse(text: &str) -> LrcDocument {
+        if text.is_empty() {
+            return LrcDocument::default();
+        }
+        let trailing_newline = text.ends_with('\n');
+        let body = text.strip_suffix('\n').unwrap_or(text);
+        let raw_lines: Vec<&str> = body.split('\n').collect();
+
+        // Only treat the document as CRLF if every line break is one, otherwise
+        // the stray `\r`s stay part of their lines.
+        let breaks = if trailing_newline { raw_lines.len() } else { raw_lines.len() - 1 };
+        let crlf = breaks > 0
+            && raw_lines
+                .iter()
+                .take(breaks)
+                .all(|line| line.ends_with('\r'));
+
+        let lines = raw_lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                if crlf && index < breaks { &line[..line.len() - 1] } else { line }
+            })
+            .map(LrcLine::parse)
+            .collect();
+
+        LrcDocument {
+            lines,
+            crlf,
+            trailing_newline,
+        }
+    }
+
+    /// Value of the first `[key:...]` tag, trimmed.
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags()
+            .find(|tag| tag.key.eq_ignore_ascii_case(key))
+            .map(|tag| tag.value.trim())
+    }
+
+    pub fn tags(&self) -> impl Iterator<Item = &IdTag> {
+        self.lines.iter().filter_map(|line| match line {
+            LrcLine::Tag(tag) => Some(tag),
+            _ => None,
+        })
+    }
+
+    /// Replaces the value of an existing tag, or inserts it after the leading
+    /// block of tags.
+    pub fn set_tag(&mut self, key: &str, value: &str) {
+        let existing = self.lines.iter_mut().find_map(|line| match line {
+            LrcLine::Tag(tag) if tag.key.eq_ignore_ascii_case(key) => Some(tag),
+            _ => None,
+        });
+        match existing {
+            Some(tag) => tag.value = value.to_owned(),
+            None => {
+                let position = self
+                    .lines
+                    .iter()
+                    .position(|line| !matches!(line, LrcLine::Tag(_)))
+                    .unwrap_or(self.lines.len());
+                let tag = IdTag {
+                    key: key.to_owned(),
+                    value: value.to_owned(),
+                };
+                self.lines.insert(position, LrcLine::Tag(tag));
+            }
+        }
+    }
+
+    pub fn remove_tag(&mut self, key: &str) {
+        self.lines
+            .retain(|line| !matches!(line, LrcLine::Tag(tag) if tag.key.eq_ignore_ascii_case(key)));
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        self.tag(Self::ARTIST)
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.tag(Self::TITLE)
+    }
+
+    pub fn album(&self) -> Option<&str> {
+        self.tag(Self::ALBUM)
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.tag(Self::AUTHOR)
+    }
+
+    /// `[length:mm:ss]` (fractions allowed) as a duration.
+    pub fn length(&self) -> Option<Duration> {
+        let value = self.tag(Self::LENGTH)?;
+        LrcTimestamp::parse(value).map(|timestamp| timestamp.as_duration())
+    }
+
+    /// `[offset:+/-ms]`. Positive values make lyrics appear sooner.
+    pub fn offset(&self) -> Option<i64> {
+        self.tag(Self::OFFSET)?.trim_start_matches('+').parse().ok()
+    }
+
+    /// Moves every timestamp by `millis`: later when positive, earlier when
+    /// negative. Times that would become negative are clamped to zero.
+    pub fn shift(&mut self, millis: i64) {
+        for line in self.lines.iter_mut() {
+            if let LrcLine::Timed(timed) = line {
+                for timestamp in timed.timestamps.iter_mut() {
+                    *timestamp = timestamp.shifted(millis);
+                }
+                timed.shift_words(millis);
+            }
+        }
+    }
+
+    /// Same effect as [`LrcDocument::shift`], recorded in the `[offset:]` tag
+    /// instead. The tag is removed when the offset ends up at zero.
+    pub fn add_offset(&mut self, millis: i64) {
+        let offset = self.offset().unwrap_or(0).saturating_sub(millis);
+        if offset == 0 {
+            self.remove_tag(Self::OFFSET);
+        } else {
+            self.set_tag(Self::OFFSET, &format!("{:+}", offset));
+        }
+    }
+
+    pub fn timed_lines(&self) -> impl Iterator<Item = &TimedLine> {
+        self.lines.iter().filter_map(|line| match line {
+            LrcLine::Timed(timed) => Some(timed),
+            _ => None,
+        })
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.timed_lines().next().is_some()
+    }
+
+    /// Every (timestamp, text) pair, with lines that carry several timestamps
+    /// expanded, sorted by time. Word times are left out of the text.
+    pub fn synced_lines(&self) -> Vec<(LrcTimestamp, String)> {
+        let mut lines: Vec<(LrcTimestamp, String)> = self
+            .timed_lines()
+            .flat_map(|line| {
+                let lyric = line.lyric();
+                line.timestamps.iter().map(move |timestamp| (*timestamp, lyric.clone()))
+            })
+            .collect();
+        lines.sort_by_key(|(timestamp, _)| timestamp.millis);
+        lines
+    }
+
+    pub fn has_word_timings(&self) -> bool {
+        self.timed_lines().any(TimedLine::has_word_timings)
+    }
+
+    /// Adds approximate word times to every line that has none, spreading each
+    /// line over the time until the next line starts (or the end of the track,
+    /// in milliseconds). Lines that repeat under several timestamps are split
+    /// into one line per timestamp, since word times are absolute.
+    pub fn synthesize_word_timings(&mut self, track_end: Option<u64>) {
+        let mut starts: Vec<u64> = self.synced_lines().iter().map(|(timestamp, _)| timestamp.millis).collect();
+        starts.dedup();
+        let end_of = |start: u64| {
+            starts
+                .iter()
+                .copied()
+                .find(|next| *next > start)
+                .or(track_end.filter(|end| *end > start))
+                .unwrap_or(start + Self::LAST_LINE_MILLIS)
+        };
+
+        let mut lines = Vec::with_capacity(self.lines.len());
+        for line in self.lines.drain(..) {
+            match line {
+                LrcLine::Timed(timed) if !timed.has_word_timings() && !timed.text.trim().is_empty() => {
+                    for timestamp in &timed.timestamps {
+                        let mut single = TimedLine { timestamps: vec![*timestamp], text: String::new() };
+                        single.set_words(&TimedLine::synthesize_words(&timed.text, *timestamp, end_of(timestamp.millis)));
+                        lines.push(LrcLine::Timed(single));
+                    }
+                }
+                line => lines.push(line),
+            }
+        }
+        self.lines = lines;
+    }
+
+    /// The latest timestamp of any line.
+    pub fn last_timestamp(&self) -> Option<LrcTimestamp> {
+        self.timed_lines()
+            .flat_map(|line| line.timestamps.iter().copied())
+            .max_by_key(|timestamp| timestamp.millis)
+    }
+
+    /// The last timestamp, if it is more than `tolerance` seconds after the
+    /// end of a track lasting `duration` seconds. An unknown length never
+    /// counts as overrun.
+    pub fn runs_past(&self, duration: Option<f64>, tolerance: f64) -> Option<LrcTimestamp> {
+        let duration = duration.filter(|seconds| *seconds > 0.0)?;
+        self.last_timestamp().filter(|last| last.millis as f64 / 1000.0 > duration + tolerance)
+    }
+
+    /// The lyric text without tags or timestamps.
+    pub fn plain_text(&self) -> String {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                LrcLine::Tag(_) => None,
+                LrcLine::Timed(timed) => Some(timed.lyric()),
+                LrcLine::Untimed(text) => Some(text.trim_end_matches('\r').to_owned()),
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl Display for LrcDocument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let line_break = if self.crlf { "\r\n" } else { "\n" };
+        for (index, line) in self.lines.iter().enumerate() {
+            if index > 0 {
+                write!(f, "{}", line_break)?;
+            }
+            write!(f, "{}", line)?;
+        }
+        if self.trailing_newline {
+            write!(f, "{}", line_break)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for LrcDocument {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(LrcDocument::parse(s))
+    }
+}
@@ -0,0 +1,151 @@
+#[cfg(test)]
+mod lrc_tests {
+    use std::time::Duration;
+    use crate::model::lrc::{LrcDocument, LrcLine, LrcTimestamp, TimedWord};
+
+    const SAMPLE: &str = "[ar: Benny Blanco]\n[ti:Roses]\n[al:Roses]\n[length:03:43]\n[offset:+250]\n[by:someone]\n\n[00:12.00]First line\n[00:15.30][01:02.50]Chorus line\n[1:05.123]Three digit fraction\nNot timed at all\n[00:20]No fraction\n";
+
+    #[test]
+    fn test_round_trip_is_lossless() {
+        let inputs = [
+            SAMPLE,
+            "",
+            "\n",
+            "plain lyric\nwithout any tags",
+            "[00:01.00]a\r\n[00:02.00]b\r\n",
+            "mixed\r\nline\nendings\r\n",
+            "[00:01.00] leading space\n[00:61.00]bad seconds\n[xx:01.00]bad minutes\n[00:01.00 unterminated",
+            "  [00:01.00]indented\n[00:01:50]colon fraction\n[ar:unterminated tag",
+        ];
+        for input in inputs {
+            assert_eq!(LrcDocument::parse(input).to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_id_tags() {
+        let document = LrcDocument::parse(SAMPLE);
+        assert_eq!(document.artist(), Some("Benny Blanco"));
+        assert_eq!(document.title(), Some("Roses"));
+        assert_eq!(document.album(), Some("Roses"));
+        assert_eq!(document.author(), Some("someone"));
+        assert_eq!(document.length(), Some(Duration::from_secs(223)));
+        assert_eq!(document.offset(), Some(250));
+        assert_eq!(document.tag("missing"), None);
+    }
+
+    #[test]
+    fn test_timed_lines() {
+        let document = LrcDocument::parse(SAMPLE);
+        assert!(document.is_synced());
+
+        let synced = document.synced_lines();
+        let times: Vec<u64> = synced.iter().map(|(timestamp, _)| timestamp.millis).collect();
+        assert_eq!(times, vec![12_000, 15_300, 20_000, 62_500, 65_123]);
+        assert_eq!(synced[3].1, "Chorus line");
+        assert_eq!(document.last_timestamp().map(|timestamp| timestamp.millis), Some(65_123));
+
+        let untimed = document
+            .lines
+            .iter()
+            .filter(|line| matches!(line, LrcLine::Untimed(_)))
+            .count();
+        assert_eq!(untimed, 2);
+        assert!(!LrcDocument::parse("just words").is_synced());
+        assert_eq!(LrcDocument::parse("just words").last_timestamp(), None);
+
+        assert_eq!(document.runs_past(Some(60.0), 2.0).map(|timestamp| timestamp.millis), Some(65_123));
+        assert_eq!(document.runs_past(Some(64.0), 2.0), None);
+        assert_eq!(document.runs_past(None, 0.0), None);
+    }
+
+    #[test]
+    fn test_set_tag() {
+        let mut document = LrcDocument::parse("[ar:Old]\n[00:01.00]line\n");
+        document.set_tag("ar", "New");
+        document.set_tag("ti", "Title");
+        assert_eq!(document.to_string(), "[ar:New]\n[ti:Title]\n[00:01.00]line\n");
+
+        document.remove_tag("ar");
+        assert_eq!(document.to_string(), "[ti:Title]\n[00:01.00]line\n");
+    }
+
+    #[test]
+    fn test_timestamp_keeps_layout() {
+        let document = LrcDocument::parse("[1:05.123]x");
+        let LrcLine::Timed(line) = &document.lines[0] else {
+            panic!("expected a timed line");
+        };
+        assert_eq!(line.timestamps[0].with_millis(125_004).to_string(), "[2:05.004]");
+        assert_eq!(LrcTimestamp::from_millis(83_456).to_string(), "[01:23.46]");
+    }
+
+    #[test]
+    fn test_oversized_timestamps_are_untimed() {
+        let long_minutes = format!("[{}1:00]x", "0".repeat(300));
+        for text in ["[999999999999999999:00]x", "[307445734561825860:15.999]x", long_minutes.as_str()] {
+            let document = LrcDocument::parse(text);
+            assert!(matches!(document.lines[0], LrcLine::Untimed(_)), "{}", text);
+            assert!(!document.is_synced());
+        }
+    }
+
+    #[test]
+    fn test_plain_text() {
+        let document = LrcDocument::parse("[ar:A]\n[00:01.00] one\n[00:02.00]two\nthree");
+        assert_eq!(document.plain_text(), "one\ntwo\nthree");
+    }
+
+    #[test]
+    fn test_shift_and_offset() {
+        let mut document = LrcDocument::parse("[00:01.00]a\n[1:00.5]b");
+        document.shift(-1500);
+        assert_eq!(document.to_string(), "[00:00.00]a\n[0:59.0]b");
+
+        let mut document = LrcDocument::parse("[offset:+200]\n[00:01.00]a");
+        document.add_offset(300);
+        assert_eq!(document.offset(), Some(-100));
+        document.add_offset(-100);
+        assert_eq!(document.to_string(), "[00:01.00]a");
+    }
+
+    #[test]
+    fn test_enhanced_word_timings() {
+        let text = "[00:12.00]<00:12.00>Never <00:12.50>gonna <00:13.10>give<00:13.80>\n[00:14.00]Plain line";
+        let document = LrcDocument::parse(text);
+        assert_eq!(document.to_string(), text);
+        assert!(document.has_word_timings());
+
+        let lines: Vec<_> = document.timed_lines().collect();
+        let words = lines[0].words();
+        assert_eq!(words.len(), 4);
+        assert_eq!(words[1], TimedWord { timestamp: LrcTimestamp::from_millis(12_500), text: String::from("gonna ") });
+        assert_eq!(words[3].text, "");
+        assert_eq!(lines[0].lyric(), "Never gonna give");
+        assert!(lines[1].words().is_empty());
+        assert_eq!(document.synced_lines()[0].1, "Never gonna give");
+        assert_eq!(document.plain_text(), "Never gonna give\nPlain line");
+    }
+
+    #[test]
+    fn test_shift_moves_word_timings() {
+        let mut document = LrcDocument::parse("[00:01.00]<00:01.00>a <00:01.50>b <not a time>");
+        document.shift(250);
+        assert_eq!(document.to_string(), "[00:01.25]<00:01.25>a <00:01.75>b <not a time>");
+    }
+
+    #[test]
+    fn test_synthesize_word_timings() {
+        let mut document = LrcDocument::parse("[ti:T]\n[00:01.00]aa bb cccc\n[00:02.00][00:05.00]x\n[00:03.00]<00:03.00>kept");
+        document.synthesize_word_timings(Some(6000));
+        assert_eq!(
+            document.to_string(),
+            "[ti:T]\n[00:01.00]<00:01.00>aa <00:01.25>bb <00:01.50>cccc<00:02.00>\n\
+             [00:02.00]<00:02.00>x<00:03.00>\n[00:05.00]<00:05.00>x<00:06.00>\n[00:03.00]<00:03.00>kept"
+        );
+
+        let mut last_line = LrcDocument::parse("[00:01.00]end");
+        last_line.synthesize_word_timings(None);
+        assert_eq!(last_line.to_string(), "[00:01.00]<00:01.00>end<00:05.00>");
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::model::data_model::{Lyric, SongMetadata};
+use crate::model::lrc::{LrcDocument, LrcLine, LrcTimestamp};
+
+/// Markup such as `<br>` or `</i>`. Enhanced LRC word times (`<00:12.34>`)
+/// start with a digit and are left alone.
+static HTML_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<\s*/?\s*[A-Za-z][^<>]*>").unwrap());
+static HTML_ENTITY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"&(amp|lt|gt|quot|apos|nbsp|#\d+|#x[0-9A-Fa-f]+);").unwrap());
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Severity {
+    /// The lyric is usable, possibly after a repair.
+    Warning,
+    /// The timings can't be trusted; only the text is kept.
+    Fatal,
+}
+
+/// A problem found in a synced lyric.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum SyncIssue {
+    /// Lines whose first timestamp is earlier than the line before.
+    OutOfOrder { lines: usize },
+    /// Timestamps that already appeared on an earlier line.
+    DuplicateTimestamps { count: usize },
+    /// Lines containing HTML tags or entities.
+    Html { lines: usize },
+    /// Every timestamp is `00:00.00`.
+    AllZero,
+    /// The last timestamp is after the end of the track.
+    PastEnd { last_millis: u64, length_millis: u64 },
+}
+
+impl SyncIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            SyncIssue::AllZero | SyncIssue::PastEnd { .. } => Severity::Fatal,
+            _ => Severity::Warning,
+        }
+    }
+
+    /// Whether [`LrcValidator::repair`] fixes it.
+    pub fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            SyncIssue::OutOfOrder { .. } | SyncIssue::DuplicateTimestamps { .. } | SyncIssue::Html { .. }
+        )
+    }
+}
+
+impl Display for SyncIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncIssue::OutOfOrder { lines } => write!(f, "{} lines out of order", lines),
+            SyncIssue::DuplicateTimestamps { count } => write!(f, "{} duplicate timestamps", count),
+            SyncIssue::Html { lines } => write!(f, "HTML in {} lines", lines),
+            SyncIssue::AllZero => write!(f, "every timestamp is zero"),
+            SyncIssue::PastEnd { last_millis, length_millis } => write!(
+                f,
+                "last line at {} is past the end of the track ({})",
+                LrcTimestamp::from_millis(*last_millis),
+                LrcTimestamp::from_millis(*length_millis)
+            ),
+        }
+    }
+}
+
+/// What validation found in a lyric and what was done about it.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ValidationReport {
+    /// Whether the lyric was synced and therefore checked.
+    pub checked: bool,
+    pub issues: Vec<SyncIssue>,
+    /// The repairable issues were fixed.
+    pub repaired: bool,
+}
+
+impl ValidationReport {
+    pub fn is_fatal(&self) -> bool {
+        self.issues.iter().any(|issue| issue.severity() == Severity::Fatal)
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Warnings that are still present in the lyric that gets written.
+    pub fn has_open_warnings(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| issue.severity() == Severity::Warning && !(self.repaired && issue.is_repairable()))
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.issues.is_empty() {
+            return write!(f, "no problems");
+        }
+        let issues: Vec<String> = self
+            .issues
+            .iter()
+            .map(|issue| match issue.severity() {
+                Severity::Fatal => format!("{} (fatal)", issue),
+                Severity::Warning if self.repaired && issue.is_repairable() => format!("{} (repaired)", issue),
+                Severity::Warning => issue.to_string(),
+            })
+            .collect();
+        write!(f, "{}", issues.join(", "))
+    }
+}
+
+/// Checks synced lyrics before they are written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LrcValidator {
+    /// Sort lines, drop duplicate timestamps and strip HTML.
+    pub repair: bool,
+    /// Seconds the last line may start after the end of the track.
+    pub duration_tolerance: f64,
+}
+
+impl Default for LrcValidator {
+    fn default() -> Self {
+        LrcValidator {
+            repair: false,
+            duration_tolerance: LrcDocument::DURATION_TOLERANCE,
+        }
+    }
+}
+
+impl LrcValidator {
+    pub fn with_repair(mut self, repair: bool) -> Self {
+        self.repair = repair;
+        self
+    }
+
+    pub fn with_duration_tolerance(mut self, seconds: f64) -> Self {
+        self.duration_tolerance = seconds.max(0.0);
+        self
+    }
+
+    /// Lists the problems of `document`. `duration` is the track length in seconds.
+    pub fn validate(&self, document: &LrcDocument, duration: Option<f64>) -> Vec<SyncIssue> {
+        let mut issues = Vec::new();
+        let timed: Vec<_> = document.timed_lines().collect();
+
+        let out_of_order = timed
+            .windows(2)
+            .filter(|pair| pair[1].timestamps[0].millis < pair[0].timestamps[0].millis)
+            .count();
+        if out_of_order > 0 {
+            issues.push(SyncIssue::OutOfOrder { lines: out_of_order });
+        }
+
+        let mut seen = HashSet::new();
+        let duplicates = timed
+            .iter()
+            .flat_map(|line| &line.timestamps)
+            .filter(|timestamp| !seen.insert(timestamp.millis))
+            .count();
+        if duplicates > 0 {
+            issues.push(SyncIssue::DuplicateTimestamps { count: duplicates });
+        }
+
+        let html = document
+            .lines
+            .iter()
+            .filter(|line| match line {
+                LrcLine::Timed(timed) => Self::has_html(&timed.text),
+                LrcLine::Untimed(text) => Self::has_html(text),
+                LrcLine::Tag(_) => false,
+            })
+            .count();
+        if html > 0 {
+            issues.push(SyncIssue::Html { lines: html });
+        }
+
+        let timestamps = timed.iter().map(|line| line.timestamps.len()).sum::<usize>();
+        if timestamps > 1 && timed.iter().flat_map(|line| &line.timestamps).all(|t| t.millis == 0) {
+            issues.push(SyncIssue::AllZero);
+        }
+
+        if let (Some(duration), Some(last)) = (duration, document.runs_past(duration, self.duration_tolerance)) {
+            issues.push(SyncIssue::PastEnd {
+                last_millis: last.millis,
+                length_millis: (duration * 1000.0) as u64,
+            });
+        }
+        issues
+    }
+
+    /// Sorts timed lines by their first timestamp, drops timestamps an earlier
+    /// line already has (and lines left without any) and strips HTML. Tags
+    /// and untimed lines keep their place.
+    pub fn repair(document: &LrcDocument) -> LrcDocument {
+        let mut document = document.clone();
+        for line in document.lines.iter_mut() {
+            match line {
+                LrcLine::Timed(timed) => timed.text = Self::strip_html(&timed.text),
+                LrcLine::Untimed(text) => *text = Self::strip_html(text),
+                LrcLine::Tag(_) => {}
+            }
+        }
+
+        let slots: Vec<usize> = (0..document.lines.len())
+            .filter(|i| matches!(document.lines[*i], LrcLine::Timed(_)))
+            .collect();
+        let mut timed: Vec<LrcLine> = slots.iter().map(|i| document.lines[*i].clone()).collect();
+        timed.sort_by_key(|line| match line {
+            LrcLine::Timed(timed) => timed.timestamps[0].millis,
+            _ => 0,
+        });
+        for (slot, line) in slots.iter().zip(timed) {
+            document.lines[*slot] = line;
+        }
+
+        let mut seen = HashSet::new();
+        document.lines.retain_mut(|line| match line {
+            LrcLine::Timed(timed) => {
+                timed.timestamps.retain(|timestamp| seen.insert(timestamp.millis));
+                !timed.timestamps.is_empty()
+            }
+            _ => true,
+        });
+        document
+    }
+
+    /// Validates a synced lyric and, depending on the outcome, repairs it or
+    /// keeps only its text. Plain lyrics are returned untouched.
+    pub fn apply(&self, lyric: Lyric, song_metadata: &SongMetadata) -> (Lyric, ValidationReport) {
+        let document = lyric.document();
+        if !document.is_synced() {
+            return (lyric, ValidationReport::default());
+        }
+        let issues = self.validate(&document, song_metadata.duration);
+        let report = ValidationReport {
+            checked: true,
+            repaired: self.repair && issues.iter().any(SyncIssue::is_repairable),
+            issues,
+        };
+        if !report.repaired && !report.is_fatal() {
+            return (lyric, report);
+        }
+        let document = if report.repaired {
+            Self::repair(&document)
+        } else {
+            document
+        };
+        let text = if report.is_fatal() {
+            document.plain_text()
+        } else {
+            document.to_string()
+        };
+        (Lyric { lyric: text, song: lyric.song }, report)
+    }
+
+    fn has_html(text: &str) -> bool {
+        HTML_TAG.is_match(text) || HTML_ENTITY.is_match(text)
+    }
+
+    fn strip_html(text: &str) -> String {
+        if !Self::has_html(text) {
+            return text.to_owned();
+        }
+        let text = HTML_TAG.replace_all(text, "");
+        HTML_ENTITY
+            .replace_all(&text, |captures: &regex::Captures| match &captures[1] {
+                "amp" => String::from("&"),
+                "lt" => String::from("<"),
+                "gt" => String::from(">"),
+                "quot" => String::from("\""),
+                "apos" => String::from("'"),
+                "nbsp" => String::from(" "),
+                code => {
+                    let number = match code.strip_prefix("#x") {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => code[1..].parse().ok(),
+                    };
+                    number.and_then(char::from_u32).map(String::from).unwrap_or_default()
+                }
+            })
+            .into_owned()
+    }
+}
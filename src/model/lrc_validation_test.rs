@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod lrc_validation_tests {
+    use crate::model::data_model::{FieldSources, Lyric, Song, SongMetadata};
+    use crate::model::lrc::LrcDocument;
+    use crate::model::lrc_validation::{LrcValidator, Severity, SyncIssue};
+
+    fn metadata(duration: Option<f64>) -> SongMetadata {
+        SongMetadata {
+            song: Song::empty(),
+            artist: String::from("Artist"),
+            title: String::from("Title"),
+            album_title: String::new(),
+            duration,
+            sources: FieldSources::default(),
+        }
+    }
+
+    fn lyric(text: &str) -> Lyric {
+        Lyric { lyric: text.to_owned(), song: Song::empty() }
+    }
+
+    fn issues(text: &str, duration: Option<f64>) -> Vec<SyncIssue> {
+        LrcValidator::default().validate(&LrcDocument::parse(text), duration)
+    }
+
+    #[test]
+    fn test_well_formed_lyric_has_no_issues() {
+        let text = "[ar:Artist]\n[00:01.00]One\n[00:02.00][01:00.00]Chorus\n[00:03.00]Two\n";
+        assert_eq!(issues(text, Some(65.0)), vec![]);
+    }
+
+    #[test]
+    fn test_detects_each_problem() {
+        assert_eq!(
+            issues("[00:05.00]b\n[00:01.00]a\n[00:05.00]c", None),
+            vec![SyncIssue::OutOfOrder { lines: 1 }, SyncIssue::DuplicateTimestamps { count: 1 }]
+        );
+        assert_eq!(issues("[00:01.00]Hello<br/>\n[00:02.00]Tom &amp; Jerry", None), vec![SyncIssue::Html { lines: 2 }]);
+        assert_eq!(issues("[00:01.00]word <00:01.50>word", None), vec![]);
+
+        let zero = issues("[00:00.00]a\n[00:00.00]b", None);
+        assert!(zero.contains(&SyncIssue::AllZero));
+        assert_eq!(SyncIssue::AllZero.severity(), Severity::Fatal);
+
+        assert_eq!(
+            issues("[00:01.00]a\n[03:30.00]b", Some(200.0)),
+            vec![SyncIssue::PastEnd { last_millis: 210_000, length_millis: 200_000 }]
+        );
+        assert_eq!(issues("[00:01.00]a\n[03:21.50]b", Some(200.0)), vec![]);
+    }
+
+    #[test]
+    fn test_repair_sorts_dedupes_and_strips_html() {
+        let document = LrcDocument::parse("[ti:T]\n[00:05.00]b\n\n[00:01.00]a<i>!</i>\n[00:05.00]c\n");
+        let repaired = LrcValidator::repair(&document);
+        assert_eq!(repaired.to_string(), "[ti:T]\n[00:01.00]a!\n\n[00:05.00]b\n");
+        assert_eq!(LrcValidator::default().validate(&repaired, None), vec![]);
+    }
+
+    #[test]
+    fn test_apply_repairs_only_when_enabled() {
+        let text = "[00:02.00]b\n[00:01.00]a";
+        let (unchanged, report) = LrcValidator::default().apply(lyric(text), &metadata(None));
+        assert_eq!(unchanged.lyric, text);
+        assert!(report.checked && !report.repaired && report.has_open_warnings());
+
+        let (repaired, report) = LrcValidator::default().with_repair(true).apply(lyric(text), &metadata(None));
+        assert_eq!(repaired.lyric, "[00:01.00]a\n[00:02.00]b");
+        assert!(report.repaired && !report.has_open_warnings());
+        assert_eq!(report.to_string(), "1 lines out of order (repaired)");
+    }
+
+    #[test]
+    fn test_apply_keeps_text_of_fatal_lyrics() {
+        let (lyric, report) = LrcValidator::default().apply(lyric("[00:01.00]a\n[09:00.00]b"), &metadata(Some(200.0)));
+        assert!(report.is_fatal());
+        assert_eq!(lyric.lyric, "a\nb");
+        assert!(!lyric.is_synced());
+    }
+
+    #[test]
+    fn test_plain_lyrics_are_not_checked() {
+        let (lyric, report) = LrcValidator::default().apply(lyric("just <b>words</b>"), &metadata(None));
+        assert_eq!(lyric.lyric, "just <b>words</b>");
+        assert!(!report.checked && report.is_clean());
+    }
+}
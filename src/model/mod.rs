@@ -1,2 +1,10 @@
 pub mod data_model;
-mod data_model_test;
\ No newline at end of file
+pub mod lrc;
+pub mod lrc_validation;
+pub mod run_summary;
+mod sniff;
+mod data_model_test;
+mod lrc_test;
+mod lrc_validation_test;
+mod run_summary_test;
+mod sniff_test;
\ No newline at end of file
@@ -0,0 +1,89 @@
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::model::lrc_validation::ValidationReport;
+use crate::store::outcome_store::Outcome;
+
+/// Counts what happened to every file of a run. Shared between worker threads.
+#[derive(Debug, Default)]
+pub struct RunSummary {
+    skipped: AtomicUsize,
+    written: AtomicUsize,
+    not_found: AtomicUsize,
+    instrumental: AtomicUsize,
+    errors: AtomicUsize,
+    synced_valid: AtomicUsize,
+    synced_repaired: AtomicUsize,
+    synced_warnings: AtomicUsize,
+    synced_rejected: AtomicUsize,
+}
+
+impl RunSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_skipped(&self) {
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_outcome(&self, outcome: Outcome) {
+        let counter = match outcome {
+            Outcome::Written => &self.written,
+            Outcome::NotFound => &self.not_found,
+            Outcome::Instrumental => &self.instrumental,
+            Outcome::Error => &self.errors,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Plain lyrics are not counted.
+    pub fn record_validation(&self, report: &ValidationReport) {
+        if !report.checked {
+            return;
+        }
+        let counter = if report.is_fatal() {
+            &self.synced_rejected
+        } else if report.has_open_warnings() {
+            &self.synced_warnings
+        } else if report.repaired {
+            &self.synced_repaired
+        } else {
+            &self.synced_valid
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Files that ended with [`Outcome::Error`].
+    pub fn failed(&self) -> usize {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    pub fn processed(&self) -> usize {
+        [&self.written, &self.not_found, &self.instrumental, &self.errors]
+            .iter()
+            .map(|counter| counter.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+impl Display for RunSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let count = |counter: &AtomicUsize| counter.load(Ordering::Relaxed);
+        write!(
+            f,
+            "{} processed, {} skipped: {} written, {} not found, {} instrumental, {} failed. \
+             Synced lyrics: {} valid, {} repaired, {} with warnings, {} rejected",
+            self.processed(),
+            count(&self.skipped),
+            count(&self.written),
+            count(&self.not_found),
+            count(&self.instrumental),
+            count(&self.errors),
+            count(&self.synced_valid),
+            count(&self.synced_repaired),
+            count(&self.synced_warnings),
+            count(&self.synced_rejected),
+        )
+    }
+}
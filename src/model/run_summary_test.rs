@@ -0,0 +1,31 @@
+#[cfg(test)]
+mod run_summary_tests {
+    use crate::model::lrc_validation::{SyncIssue, ValidationReport};
+    use crate::model::run_summary::RunSummary;
+    use crate::store::outcome_store::Outcome;
+
+    #[test]
+    fn test_counts_outcomes_and_validation() {
+        let summary = RunSummary::new();
+        summary.record_skipped();
+        summary.record_outcome(Outcome::Written);
+        summary.record_outcome(Outcome::Written);
+        summary.record_outcome(Outcome::NotFound);
+        summary.record_validation(&ValidationReport::default());
+        summary.record_validation(&ValidationReport { checked: true, issues: vec![], repaired: false });
+        summary.record_validation(&ValidationReport {
+            checked: true,
+            issues: vec![SyncIssue::OutOfOrder { lines: 2 }],
+            repaired: true,
+        });
+        summary.record_validation(&ValidationReport { checked: true, issues: vec![SyncIssue::AllZero], repaired: false });
+
+        assert_eq!(summary.processed(), 3);
+        assert_eq!(summary.failed(), 0);
+        assert_eq!(
+            summary.to_string(),
+            "3 processed, 1 skipped: 2 written, 1 not found, 0 instrumental, 0 failed. \
+             Synced lyrics: 1 valid, 1 repaired, 0 with warnings, 1 rejected"
+        );
+    }
+}
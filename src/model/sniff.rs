@@ -0,0 +1,135 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use log::warn;
+
+use crate::model::data_model::{AudioExtensions, SubtitleFormat};
+use crate::model::data_model::AudioExtensions::{AAC, AIFF, FLAC, M4A, MP3, OGG, OPUS, UNKNOWN, WAV, WMA};
+
+/// Enough bytes to see past the first Ogg page header to the codec magic.
+const SNIFF_LEN: usize = 64;
+
+/// First bytes of the ASF header object GUID.
+const ASF_MAGIC: [u8; 8] = [0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11];
+
+/// ISO-BMFF major brands of audio files. Generic MP4 brands such as `isom`
+/// and `mp42` are mostly video, so like any other brand they are left to the
+/// extension.
+const AUDIO_BRANDS: [&[u8; 4]; 3] = [b"M4A ", b"M4B ", b"M4P "];
+
+impl AudioExtensions {
+    /// Format from the first bytes of a file, after any ID3v2 tag.
+    pub fn sniff(header: &[u8]) -> Option<AudioExtensions> {
+        let starts = |magic: &[u8]| header.starts_with(magic);
+        let at = |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+
+        if starts(b"fLaC") {
+            Some(FLAC)
+        } else if starts(b"OggS") {
+            let first_packet = header.get(28..).unwrap_or_default();
+            if first_packet.starts_with(b"OpusHead") {
+                Some(OPUS)
+            } else {
+                Some(OGG)
+            }
+        } else if at(4, b"ftyp") {
+            AUDIO_BRANDS.iter().any(|brand| at(8, *brand)).then_some(M4A)
+        } else if starts(b"RIFF") && at(8, b"WAVE") {
+            Some(WAV)
+        } else if starts(b"FORM") && (at(8, b"AIFF") || at(8, b"AIFC")) {
+            Some(AIFF)
+        } else if starts(&ASF_MAGIC) {
+            Some(WMA)
+        } else if header.len() >= 3 && header[0] == 0xFF && header[1] & 0xE0 == 0xE0 {
+            Self::sniff_frame(header[1], header[2])
+        } else {
+            None
+        }
+    }
+
+    /// An MPEG audio or ADTS frame header, from the two bytes after the first
+    /// sync byte. `FF FE` is also a UTF-16LE byte order mark and is never taken
+    /// for audio, so text files can't pass as MPEG-1 Layer I.
+    fn sniff_frame(second: u8, third: u8) -> Option<AudioExtensions> {
+        let layer = (second >> 1) & 0b11;
+        let bitrate = third >> 4;
+        let sample_rate = (third >> 2) & 0b11;
+        if second == 0xFE {
+            None
+        } else if layer == 0 {
+            // ADTS reuses the sync with the layer bits set to 0.
+            let frequency = (third >> 2) & 0b1111;
+            (second & 0xF0 == 0xF0 && frequency < 13).then_some(AAC)
+        } else if bitrate != 0xF && bitrate != 0 && sample_rate != 3 {
+            Some(MP3)
+        } else {
+            None
+        }
+    }
+
+    /// Sniffs the start of the file at `path`, skipping a leading ID3v2 tag.
+    /// Lyric and subtitle files are never audio, whatever their content.
+    pub fn sniff_file(path: &Path) -> Option<AudioExtensions> {
+        if Self::is_lyric_output(path) {
+            return None;
+        }
+        let mut file = File::open(path).ok()?;
+        let mut header = Self::read_head(&mut file)?;
+        if header.starts_with(b"ID3") && header.len() >= 10 {
+            let size = header[6..10]
+                .iter()
+                .fold(0u64, |size, byte| (size << 7) | u64::from(byte & 0x7F));
+            let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+            file.seek(SeekFrom::Start(10 + size + footer)).ok()?;
+            header = Self::read_head(&mut file)?;
+        }
+        Self::sniff(&header)
+    }
+
+    /// `.lrc` or one of the subtitle formats this tool writes.
+    fn is_lyric_output(path: &Path) -> bool {
+        Self::has_extension(path, "lrc")
+            || SubtitleFormat::ALL
+                .iter()
+                .any(|format| Self::has_extension(path, format.extension()))
+    }
+
+    fn has_extension(path: &Path, extension: &str) -> bool {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case(extension))
+    }
+
+    fn read_head(file: &mut File) -> Option<Vec<u8>> {
+        let mut header = Vec::with_capacity(SNIFF_LEN);
+        file.take(SNIFF_LEN as u64).read_to_end(&mut header).ok()?;
+        Some(header)
+    }
+
+    /// The format of `path` by content, with the extension as a fallback for
+    /// files that can't be read or recognized. Disagreements are logged.
+    pub fn detect(path: &Path) -> AudioExtensions {
+        let hinted = Self::get_extension_by_filepath(path);
+        match Self::sniff_file(path) {
+            Some(sniffed) if hinted != UNKNOWN && !sniffed.is_compatible_with(hinted) => {
+                warn!(
+                    "{} has a .{} extension but its content is {}",
+                    path.display(),
+                    path.extension().and_then(|e| e.to_str()).unwrap_or_default(),
+                    sniffed
+                );
+                sniffed
+            }
+            Some(sniffed) => sniffed,
+            // `.mp4` is an M4A alias, but without an audio brand it is a video.
+            None if Self::has_extension(path, "mp4") => UNKNOWN,
+            None => hinted,
+        }
+    }
+
+    /// Opus is commonly stored with an `.ogg` extension.
+    fn is_compatible_with(&self, other: AudioExtensions) -> bool {
+        *self == other || matches!((self, other), (OPUS, OGG) | (OGG, OPUS))
+    }
+}
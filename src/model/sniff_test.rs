@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod sniff_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::metadata::metadata_extractor::MetadataExtractor;
+    use crate::model::data_model::{AudioExtensions, Song};
+    use crate::parallel_helper::parallel_helper::ParallelHelper;
+
+    #[test]
+    fn test_sniff_every_fixture() {
+        let fixtures = vec![
+            ("benny_blanco-roses.mp3", AudioExtensions::MP3),
+            ("fixture.ogg", AudioExtensions::OGG),
+            ("fixture.opus", AudioExtensions::OPUS),
+            ("fixture.m4a", AudioExtensions::M4A),
+            ("fixture.aac", AudioExtensions::AAC),
+            ("fixture.wav", AudioExtensions::WAV),
+            ("fixture.aiff", AudioExtensions::AIFF),
+            ("fixture.wma", AudioExtensions::WMA),
+        ];
+        for (name, expected) in fixtures {
+            let path = PathBuf::from("test_resources").join(name);
+            assert_eq!(AudioExtensions::sniff_file(&path), Some(expected), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_sniff_magic_bytes() {
+        assert_eq!(AudioExtensions::sniff(b"fLaC\0\0\0\x22"), Some(AudioExtensions::FLAC));
+        assert_eq!(AudioExtensions::sniff(&[0xFF, 0xFB, 0x90, 0x64]), Some(AudioExtensions::MP3));
+        assert_eq!(AudioExtensions::sniff(&[0xFF, 0xF1, 0x50, 0x80]), Some(AudioExtensions::AAC));
+        assert_eq!(AudioExtensions::sniff(b"\x89PNG\r\n\x1a\n"), None);
+        assert_eq!(AudioExtensions::sniff(b""), None);
+    }
+
+    #[test]
+    fn test_sniff_rejects_invalid_frame_headers() {
+        // Bitrate index 0xF, sample rate index 3, and a UTF-16LE BOM before `[`.
+        assert_eq!(AudioExtensions::sniff(&[0xFF, 0xFB, 0xF0, 0x64]), None);
+        assert_eq!(AudioExtensions::sniff(&[0xFF, 0xFB, 0x9C, 0x64]), None);
+        assert_eq!(AudioExtensions::sniff(&[0xFF, 0xFE, b'[', 0x00]), None);
+        assert_eq!(AudioExtensions::sniff(&[0xFF, 0xF1, 0x7C, 0x80]), None);
+    }
+
+    #[test]
+    fn test_utf16_text_files_are_not_songs() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend("[ar:Artist]".encode_utf16().flat_map(u16::to_le_bytes));
+        for name in ["Artist - Title.lrc", "Artist - Title.srt", "notes"] {
+            fs::write(tmp_dir.path().join(name), &utf16).unwrap();
+        }
+        // A song without an extension is still found by its content.
+        fs::copy("test_resources/benny_blanco-roses.mp3", tmp_dir.path().join("roses")).unwrap();
+
+        let found = ParallelHelper::collect_audio_files(tmp_dir.path(), false);
+        assert_eq!(found, vec![tmp_dir.path().join("roses")]);
+        let lrc = tmp_dir.path().join("Artist - Title.lrc");
+        assert!(Song::new(lrc.to_str().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_sniff_iso_bmff_brands() {
+        assert_eq!(AudioExtensions::sniff(b"\0\0\0\x18ftypM4B \0\0\0\0"), Some(AudioExtensions::M4A));
+        assert_eq!(AudioExtensions::sniff(b"\0\0\0\x18ftypM4P \0\0\0\0"), Some(AudioExtensions::M4A));
+        assert_eq!(AudioExtensions::sniff(b"\0\0\0\x18ftypisom\0\0\0\0"), None);
+        assert_eq!(AudioExtensions::sniff(b"\0\0\0\x18ftypmp42\0\0\0\0"), None);
+        assert_eq!(AudioExtensions::sniff(b"\0\0\0\x18ftypqt  \0\0\0\0"), None);
+        assert_eq!(AudioExtensions::sniff(b"\0\0\0\x18ftypheic\0\0\0\0"), None);
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let video = tmp_dir.path().join("clip.m4a");
+        fs::write(&video, b"\0\0\0\x18ftyp3gp4\0\0\0\0").unwrap();
+        assert_eq!(AudioExtensions::detect(&video), AudioExtensions::M4A);
+        let image = tmp_dir.path().join("photo.flac");
+        fs::write(&image, b"\0\0\0\x18ftypheic\0\0\0\0").unwrap();
+        assert_eq!(AudioExtensions::detect(&image), AudioExtensions::FLAC);
+
+        let movie = tmp_dir.path().join("movie.mp4");
+        fs::write(&movie, b"\0\0\0\x18ftypisom\0\0\0\0").unwrap();
+        assert_eq!(AudioExtensions::detect(&movie), AudioExtensions::UNKNOWN);
+        assert!(Song::new(movie.to_str().unwrap()).is_none());
+        let audio = tmp_dir.path().join("audio.mp4");
+        fs::copy("test_resources/fixture.m4a", &audio).unwrap();
+        assert_eq!(AudioExtensions::detect(&audio), AudioExtensions::M4A);
+    }
+
+    #[test]
+    fn test_content_wins_over_extension() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let misnamed = tmp_dir.path().join("actually_m4a.mp3");
+        fs::copy("test_resources/fixture.m4a", &misnamed).unwrap();
+
+        let song = Song::new(misnamed.to_str().unwrap()).unwrap();
+        assert_eq!(song.extension, AudioExtensions::M4A);
+        let metadata = MetadataExtractor::extract(&song).unwrap();
+        assert_eq!(metadata.title, "Fixture");
+    }
+
+    #[test]
+    fn test_files_without_extension_are_sniffed() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let no_extension = tmp_dir.path().join("roses");
+        fs::copy("test_resources/benny_blanco-roses.mp3", &no_extension).unwrap();
+
+        let song = Song::new(no_extension.to_str().unwrap()).unwrap();
+        assert_eq!(song.extension, AudioExtensions::MP3);
+
+        let text = tmp_dir.path().join("notes");
+        fs::write(&text, "not audio").unwrap();
+        assert!(Song::new(text.to_str().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_extension_is_the_fallback() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let unreadable = tmp_dir.path().join("empty.flac");
+        fs::write(&unreadable, "").unwrap();
+
+        assert_eq!(AudioExtensions::detect(&unreadable), AudioExtensions::FLAC);
+        assert_eq!(AudioExtensions::detect(&PathBuf::from("missing.opus")), AudioExtensions::OPUS);
+    }
+}
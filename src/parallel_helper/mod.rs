@@ -1 +1,2 @@
+#[allow(clippy::module_inception)]
 pub mod parallel_helper;
\ No newline at end of file
@@ -7,8 +7,27 @@ pub struct ParallelHelper;
 impl ParallelHelper {
     // traverse to collect all supported audio files
     pub fn collect_audio_files(dir: &Path, recursive: bool) -> Vec<std::path::PathBuf> {
-        let audio_exts = [AudioExtensions::MP3, AudioExtensions::FLAC];
+        Self::collect_files(dir, recursive)
+            .into_iter()
+            .filter(|path| {
+                AudioExtensions::get_extension_by_filepath(path) != AudioExtensions::UNKNOWN
+                    || AudioExtensions::sniff_file(path).is_some()
+            })
+            .collect()
+    }
+
+    pub fn collect_lrc_files(dir: &Path, recursive: bool) -> Vec<std::path::PathBuf> {
+        Self::collect_files(dir, recursive)
+            .into_iter()
+            .filter(|path| {
+                path.extension()
+                    .and_then(|extension| extension.to_str())
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("lrc"))
+            })
+            .collect()
+    }
 
+    fn collect_files(dir: &Path, recursive: bool) -> Vec<std::path::PathBuf> {
         let walker = if recursive {
             WalkDir::new(dir).into_iter()
         } else {
@@ -19,9 +38,47 @@ impl ParallelHelper {
             .filter_map(Result::ok)
             .filter(|e| e.file_type().is_file())
             .map(|e| e.into_path())
-            .filter(|path| {
-                audio_exts.contains(&AudioExtensions::get_extension_by_filepath(path))
-            })
             .collect()
     }
 }
+
+#[cfg(test)]
+mod test_parallel_helper {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn collects_every_supported_format_and_sniffed_files() -> anyhow::Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let nested = tmp_dir.path().join("nested");
+        fs::create_dir(&nested)?;
+        for extension in AudioExtensions::SUPPORTED {
+            fs::write(tmp_dir.path().join(format!("song.{}", extension)), "")?;
+        }
+        fs::write(tmp_dir.path().join("cover.jpg"), "")?;
+        fs::write(nested.join("deep.OPUS"), "")?;
+        fs::copy("test_resources/fixture.opus", tmp_dir.path().join("no_extension"))?;
+
+        let top_level = ParallelHelper::collect_audio_files(tmp_dir.path(), false);
+        assert_eq!(top_level.len(), AudioExtensions::SUPPORTED.len() + 1);
+        assert!(top_level.contains(&tmp_dir.path().join("no_extension")));
+
+        let recursive = ParallelHelper::collect_audio_files(tmp_dir.path(), true);
+        assert_eq!(recursive.len(), AudioExtensions::SUPPORTED.len() + 2);
+        Ok(())
+    }
+
+    #[test]
+    fn collects_lrc_files() -> anyhow::Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let nested = tmp_dir.path().join("nested");
+        fs::create_dir(&nested)?;
+        fs::write(tmp_dir.path().join("song.lrc"), "")?;
+        fs::write(tmp_dir.path().join("song.mp3"), "")?;
+        fs::write(nested.join("deep.LRC"), "")?;
+
+        assert_eq!(ParallelHelper::collect_lrc_files(tmp_dir.path(), false), vec![tmp_dir.path().join("song.lrc")]);
+        assert_eq!(ParallelHelper::collect_lrc_files(tmp_dir.path(), true).len(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use redb::{Database, ReadableDatabase, ReadableTable, ReadableTableMetadata, TableDefinition};
+use serde::{Deserialize, Serialize};
+
+use crate::model::data_model::SongMetadata;
+use crate::store::outcome_store::{now, OutcomeStore};
+
+/// JSON encoded [`CacheKey`] -> JSON encoded [`CachedLookup`].
+const LOOKUPS: TableDefinition<&str, &str> = TableDefinition::new("lookups");
+
+/// What a provider was asked. Text fields are lowercased with collapsed
+/// whitespace and the duration is rounded to whole seconds, so trivial tag
+/// differences share an entry.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CacheKey {
+    pub provider: String,
+    /// The provider's [`LyricIface::base_url`](crate::traits::traits::LyricIface::base_url),
+    /// so a mirror does not reuse the answers of the public instance.
+    #[serde(default)]
+    pub base_url: String,
+    /// Distinguishes configurations of a provider that answer differently,
+    /// e.g. `synced` and `plain`.
+    pub variant: String,
+    pub artist: String,
+    pub title: String,
+    pub album: String,
+    pub duration: Option<u64>,
+}
+
+impl CacheKey {
+    pub fn new(provider: &str, variant: &str, song_metadata: &SongMetadata) -> Self {
+        CacheKey {
+            provider: provider.to_owned(),
+            base_url: String::new(),
+            variant: variant.to_owned(),
+            artist: Self::normalize(&song_metadata.artist),
+            title: Self::normalize(&song_metadata.title),
+            album: Self::normalize(&song_metadata.album_title),
+            duration: song_metadata.duration.map(|seconds| seconds.round() as u64),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.to_owned();
+        self
+    }
+
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("Cache keys are always serializable")
+    }
+
+    pub fn decode(key: &str) -> Option<Self> {
+        serde_json::from_str(key).ok()
+    }
+
+    fn normalize(text: &str) -> String {
+        text.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase()
+    }
+}
+
+impl Display for CacheKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {} - {}", self.provider, self.variant, self.artist, self.title)?;
+        if !self.album.is_empty() {
+            write!(f, " [{}]", self.album)?;
+        }
+        if let Some(duration) = self.duration {
+            write!(f, " {}s", duration)?;
+        }
+        Ok(())
+    }
+}
+
+/// A provider's answer worth remembering. Transient failures are never cached.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CachedAnswer {
+    Found { lyric: String },
+    NotFound,
+    Instrumental,
+}
+
+impl CachedAnswer {
+    /// Found and instrumental answers are kept for [`CacheTtl::hit`], misses for [`CacheTtl::miss`].
+    pub fn is_hit(&self) -> bool {
+        !matches!(self, CachedAnswer::NotFound)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CachedLookup {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub answer: CachedAnswer,
+}
+
+impl CachedLookup {
+    pub fn new(answer: CachedAnswer) -> Self {
+        CachedLookup {
+            timestamp: now(),
+            answer,
+        }
+    }
+
+    pub fn is_fresh(&self, ttl: &CacheTtl, now: u64) -> bool {
+        let ttl = if self.answer.is_hit() { ttl.hit } else { ttl.miss };
+        now.saturating_sub(self.timestamp) < ttl.as_secs()
+    }
+}
+
+/// How long answers are reused. A zero TTL stops that kind of answer from being cached.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CacheTtl {
+    pub hit: Duration,
+    pub miss: Duration,
+}
+
+impl Default for CacheTtl {
+    fn default() -> Self {
+        CacheTtl {
+            hit: Duration::from_secs(30 * 24 * 60 * 60),
+            miss: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+/// On-disk cache of provider answers, in its own redb file next to the state store.
+pub struct LookupCache {
+    db: Database,
+}
+
+impl LookupCache {
+    pub const FILENAME: &'static str = "cache.redb";
+
+    /// Opens `~/.getlyrics/cache.redb`, creating it if needed.
+    pub fn open_default() -> Result<Self> {
+        Self::open(&Self::default_path()?)
+    }
+
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(OutcomeStore::default_dir()?.join(Self::FILENAME))
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        }
+        let db = Database::create(path).with_context(|| format!("Failed to open lookup cache {:?}", path))?;
+
+        let txn = db.begin_write()?;
+        txn.open_table(LOOKUPS)?;
+        txn.commit()?;
+
+        Ok(Self { db })
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Result<Option<CachedLookup>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(LOOKUPS)?;
+        match table.get(key.encode().as_str())? {
+            Some(value) => Ok(Some(serde_json::from_str(value.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn put(&self, key: &CacheKey, lookup: &CachedLookup) -> Result<()> {
+        let json = serde_json::to_string(lookup)?;
+        let txn = self.db.begin_write()?;
+        {
+            let mut table = txn.open_table(LOOKUPS)?;
+            table.insert(key.encode().as_str(), json.as_str())?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Every entry. Unreadable entries are skipped.
+    pub fn all(&self) -> Result<Vec<(CacheKey, CachedLookup)>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(LOOKUPS)?;
+        let mut entries = Vec::new();
+        for entry in table.iter()? {
+            let (key, value) = entry?;
+            if let (Some(key), Ok(lookup)) = (CacheKey::decode(key.value()), serde_json::from_str(value.value())) {
+                entries.push((key, lookup));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Removes the entries `purge` selects and returns how many there were.
+    /// Unreadable entries are always removed.
+    pub fn purge(&self, purge: impl Fn(&CacheKey, &CachedLookup) -> bool) -> Result<usize> {
+        let txn = self.db.begin_write()?;
+        let removed = {
+            let mut table = txn.open_table(LOOKUPS)?;
+            let before = table.len()?;
+            table.retain(|key, value| {
+                match (CacheKey::decode(key), serde_json::from_str::<CachedLookup>(value)) {
+                    (Some(key), Ok(lookup)) => !purge(&key, &lookup),
+                    _ => false,
+                }
+            })?;
+            before - table.len()?
+        };
+        txn.commit()?;
+        Ok(removed as usize)
+    }
+}
+
+#[cfg(test)]
+mod test_lookup_cache {
+    use super::*;
+    use crate::model::data_model::{FieldSources, Song};
+
+    fn metadata(artist: &str, title: &str) -> SongMetadata {
+        SongMetadata {
+            song: Song::empty(),
+            artist: artist.to_owned(),
+            title: title.to_owned(),
+            album_title: String::from("Album"),
+            duration: Some(200.4),
+            sources: FieldSources::default(),
+        }
+    }
+
+    #[test]
+    fn keys_are_normalized() {
+        let key = CacheKey::new("lrclib", "plain", &metadata("  Benny   Blanco", "ROSES"));
+        assert_eq!(key, CacheKey::new("lrclib", "plain", &metadata("benny blanco", "Roses ")));
+        assert_eq!(key.duration, Some(200));
+        assert_ne!(key, CacheKey::new("lrclib", "synced", &metadata("benny blanco", "Roses")));
+        assert_ne!(key, key.clone().with_base_url("http://mirror.local/api"));
+        assert_eq!(CacheKey::decode(&key.encode()), Some(key.clone()));
+        assert_eq!(key.to_string(), "lrclib (plain): benny blanco - roses [album] 200s");
+    }
+
+    #[test]
+    fn put_get_purge() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let cache = LookupCache::open(&tmp_dir.path().join(LookupCache::FILENAME))?;
+        let hit = CacheKey::new("lrclib", "plain", &metadata("A", "Hit"));
+        let miss = CacheKey::new("lrclib", "plain", &metadata("A", "Miss"));
+
+        assert!(cache.get(&hit)?.is_none());
+        let found = CachedLookup::new(CachedAnswer::Found { lyric: String::from("Line") });
+        cache.put(&hit, &found)?;
+        cache.put(&miss, &CachedLookup::new(CachedAnswer::NotFound))?;
+        assert_eq!(cache.get(&hit)?, Some(found));
+        assert_eq!(cache.all()?.len(), 2);
+
+        assert_eq!(cache.purge(|_, lookup| !lookup.answer.is_hit())?, 1);
+        assert!(cache.get(&miss)?.is_none());
+        assert_eq!(cache.purge(|_, _| true)?, 1);
+        assert!(cache.all()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn hits_and_misses_expire_separately() {
+        let ttl = CacheTtl {
+            hit: Duration::from_secs(100),
+            miss: Duration::from_secs(10),
+        };
+        let hit = CachedLookup { timestamp: 1000, answer: CachedAnswer::Instrumental };
+        let miss = CachedLookup { timestamp: 1000, answer: CachedAnswer::NotFound };
+        assert!(hit.is_fresh(&ttl, 1050));
+        assert!(!miss.is_fresh(&ttl, 1050));
+        assert!(miss.is_fresh(&ttl, 1005));
+        assert!(!hit.is_fresh(&ttl, 1100));
+        assert!(!hit.is_fresh(&CacheTtl { hit: Duration::ZERO, ..ttl }, 1000));
+    }
+}
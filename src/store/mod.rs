@@ -0,0 +1,2 @@
+pub mod lookup_cache;
+pub mod outcome_store;
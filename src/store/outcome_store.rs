@@ -0,0 +1,312 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use dirs::home_dir;
+use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+
+use crate::hasher::file_hash_helper::HashMode;
+
+/// Content hash -> JSON encoded [`ProcessRecord`].
+const OUTCOMES: TableDefinition<&str, &str> = TableDefinition::new("outcomes");
+/// File path -> JSON encoded [`Fingerprint`].
+const FINGERPRINTS: TableDefinition<&str, &str> = TableDefinition::new("fingerprints");
+
+/// What happened the last time a file was processed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Written,
+    NotFound,
+    Error,
+    Instrumental,
+}
+
+impl Outcome {
+    /// Whether the outcome is final, i.e. asking again would give the same answer.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Outcome::Written | Outcome::Instrumental)
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Outcome::Written => "written",
+            Outcome::NotFound => "not found",
+            Outcome::Error => "failed",
+            Outcome::Instrumental => "instrumental",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessRecord {
+    pub path: PathBuf,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub provider: Option<String>,
+    pub outcome: Outcome,
+    pub synced: bool,
+    pub output_path: Option<PathBuf>,
+    /// Lyrics were embedded, so the song file changed after it was hashed.
+    #[serde(default)]
+    pub embedded: bool,
+    /// Consecutive attempts that ended without a final outcome.
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+impl ProcessRecord {
+    /// A record for `path` stamped with the current time.
+    pub fn new(path: &Path, outcome: Outcome) -> Self {
+        ProcessRecord {
+            path: path.to_owned(),
+            timestamp: now(),
+            provider: None,
+            outcome,
+            synced: false,
+            output_path: None,
+            embedded: false,
+            attempts: 0,
+        }
+    }
+}
+
+/// Cheap file identity used to reuse a content hash without reading the file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    pub size: u64,
+    /// Modification time in nanoseconds since the Unix epoch.
+    pub modified: u128,
+    /// Inode number, 0 where the platform has none.
+    pub inode: u64,
+    /// Hash of the file when the fingerprint was taken.
+    pub hash: String,
+    /// How `hash` was computed.
+    #[serde(default)]
+    pub mode: HashMode,
+}
+
+impl Fingerprint {
+    /// Whether `other` describes the same file state, ignoring the hash.
+    pub fn matches(&self, other: &Fingerprint) -> bool {
+        self.size == other.size && self.modified == other.modified && self.inode == other.inode
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+/// Embedded single-file state store keyed by content hash.
+///
+/// Backed by `redb`, so every write is an ACID transaction and a crash never
+/// leaves a half written record behind.
+pub struct OutcomeStore {
+    db: Database,
+}
+
+impl OutcomeStore {
+    pub const FILENAME: &'static str = "state.redb";
+
+    /// Opens `~/.getlyrics/state.redb`, creating it if needed.
+    pub fn open_default() -> Result<Self> {
+        Self::open(&Self::default_path()?)
+    }
+
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(Self::default_dir()?.join(Self::FILENAME))
+    }
+
+    /// `~/.getlyrics`, where the state store and the lookup cache live
+    /// unless configured otherwise.
+    pub fn default_dir() -> Result<PathBuf> {
+        Ok(home_dir().context("Could not resolve home directory")?.join(".getlyrics"))
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        }
+        let db = Database::create(path).with_context(|| format!("Failed to open state store {:?}", path))?;
+
+        // Make sure the table exists so read transactions never fail on a fresh file.
+        let txn = db.begin_write()?;
+        txn.open_table(OUTCOMES)?;
+        txn.open_table(FINGERPRINTS)?;
+        txn.commit()?;
+
+        Ok(Self { db })
+    }
+
+    pub fn get(&self, hash: &str) -> Result<Option<ProcessRecord>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(OUTCOMES)?;
+        match table.get(hash)? {
+            Some(value) => Ok(Some(serde_json::from_str(value.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn put(&self, hash: &str, record: &ProcessRecord) -> Result<()> {
+        let json = serde_json::to_string(record)?;
+        let txn = self.db.begin_write()?;
+        {
+            let mut table = txn.open_table(OUTCOMES)?;
+            table.insert(hash, json.as_str())?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    pub fn remove(&self, hash: &str) -> Result<bool> {
+        let txn = self.db.begin_write()?;
+        let removed = {
+            let mut table = txn.open_table(OUTCOMES)?;
+            table.remove(hash)?.is_some()
+        };
+        txn.commit()?;
+        Ok(removed)
+    }
+
+    /// Every record, keyed by hash. Unreadable entries are skipped.
+    pub fn all(&self) -> Result<Vec<(String, ProcessRecord)>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(OUTCOMES)?;
+        let mut records = Vec::new();
+        for entry in table.iter()? {
+            let (hash, value) = entry?;
+            if let Ok(record) = serde_json::from_str(value.value()) {
+                records.push((hash.value().to_owned(), record));
+            }
+        }
+        Ok(records)
+    }
+
+    pub fn get_fingerprint(&self, path: &Path) -> Result<Option<Fingerprint>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(FINGERPRINTS)?;
+        match table.get(path.to_string_lossy().as_ref())? {
+            Some(value) => Ok(Some(serde_json::from_str(value.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn put_fingerprint(&self, path: &Path, fingerprint: &Fingerprint) -> Result<()> {
+        let json = serde_json::to_string(fingerprint)?;
+        let txn = self.db.begin_write()?;
+        {
+            let mut table = txn.open_table(FINGERPRINTS)?;
+            table.insert(path.to_string_lossy().as_ref(), json.as_str())?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Imports the hashes of the old `processed_hashes.txt` store. That store
+    /// listed a track before fetching it, failures included, so the hashes
+    /// become "not found" records: upgrading does not refetch the whole library
+    /// at once, and every track is looked up again after its cooldown.
+    pub fn import_legacy_hashes(&self, hashes: &[String]) -> Result<usize> {
+        let txn = self.db.begin_write()?;
+        let mut imported = 0;
+        {
+            let mut table = txn.open_table(OUTCOMES)?;
+            let mut record = ProcessRecord::new(Path::new(""), Outcome::NotFound);
+            record.attempts = 1;
+            let record = serde_json::to_string(&record)?;
+            for hash in hashes {
+                if table.get(hash.as_str())?.is_none() {
+                    table.insert(hash.as_str(), record.as_str())?;
+                    imported += 1;
+                }
+            }
+        }
+        txn.commit()?;
+        Ok(imported)
+    }
+}
+
+#[cfg(test)]
+mod test_outcome_store {
+    use super::*;
+
+    #[test]
+    fn put_get_remove() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let store = OutcomeStore::open(&tmp_dir.path().join(OutcomeStore::FILENAME))?;
+
+        assert!(store.get("abc")?.is_none());
+
+        let mut record = ProcessRecord::new(Path::new("/music/song.mp3"), Outcome::Written);
+        record.provider = Some("lrclib".to_owned());
+        record.synced = true;
+        record.output_path = Some(PathBuf::from("/music/song.lrc"));
+        store.put("abc", &record)?;
+
+        assert_eq!(store.get("abc")?, Some(record.clone()));
+        assert_eq!(store.all()?, vec![("abc".to_owned(), record)]);
+        assert!(store.remove("abc")?);
+        assert!(!store.remove("abc")?);
+        Ok(())
+    }
+
+    #[test]
+    fn records_survive_reopening() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let path = tmp_dir.path().join(OutcomeStore::FILENAME);
+        {
+            let store = OutcomeStore::open(&path)?;
+            store.put("abc", &ProcessRecord::new(Path::new("a.mp3"), Outcome::NotFound))?;
+        }
+        let store = OutcomeStore::open(&path)?;
+        assert_eq!(store.get("abc")?.map(|record| record.outcome), Some(Outcome::NotFound));
+        Ok(())
+    }
+
+    #[test]
+    fn legacy_import_keeps_existing_records() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let store = OutcomeStore::open(&tmp_dir.path().join(OutcomeStore::FILENAME))?;
+        store.put("abc", &ProcessRecord::new(Path::new("a.mp3"), Outcome::Error))?;
+
+        let imported = store.import_legacy_hashes(&["abc".to_owned(), "def".to_owned()])?;
+        assert_eq!(imported, 1);
+        assert_eq!(store.get("abc")?.map(|record| record.outcome), Some(Outcome::Error));
+        assert_eq!(store.get("def")?.map(|record| record.outcome), Some(Outcome::NotFound));
+        Ok(())
+    }
+
+    #[test]
+    fn fingerprints_are_keyed_by_path() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let store = OutcomeStore::open(&tmp_dir.path().join(OutcomeStore::FILENAME))?;
+        let fingerprint = Fingerprint { size: 10, modified: 20, inode: 30, hash: "abc".to_owned(), mode: HashMode::Content };
+
+        assert!(store.get_fingerprint(Path::new("/music/a.flac"))?.is_none());
+        store.put_fingerprint(Path::new("/music/a.flac"), &fingerprint)?;
+        assert_eq!(store.get_fingerprint(Path::new("/music/a.flac"))?, Some(fingerprint.clone()));
+        assert!(store.get_fingerprint(Path::new("/music/b.flac"))?.is_none());
+
+        let touched = Fingerprint { modified: 21, ..fingerprint.clone() };
+        assert!(!fingerprint.matches(&touched));
+        assert!(fingerprint.matches(&Fingerprint { hash: "def".to_owned(), ..fingerprint.clone() }));
+        Ok(())
+    }
+
+    #[test]
+    fn final_outcomes() {
+        assert!(Outcome::Written.is_final());
+        assert!(Outcome::Instrumental.is_final());
+        assert!(!Outcome::NotFound.is_final());
+        assert!(!Outcome::Error.is_final());
+        assert_eq!(Outcome::NotFound.to_string(), "not found");
+    }
+}
@@ -1 +1,2 @@
+#[allow(clippy::module_inception)]
 pub mod traits;
\ No newline at end of file
@@ -1,11 +1,25 @@
+use crate::api::lyric_error::LyricError;
 use crate::model::data_model::{Lyric, SongMetadata};
+use crate::store::outcome_store::ProcessRecord;
 use anyhow::Result;
 use std::path::Path;
 
-pub trait LyricIface {
-    fn fetch_lyrics(&self, song_metadata: &SongMetadata) -> Result<Lyric, String>;
+pub trait LyricIface: Send + Sync {
+    /// Short, stable identifier used to order, disable and report providers.
+    fn name(&self) -> &str;
+    /// Where the provider sends its requests, so that answers of a mirror are
+    /// cached apart from those of the public instance. Empty if it has none.
+    fn base_url(&self) -> &str {
+        ""
+    }
+    fn fetch_lyrics(&self, song_metadata: &SongMetadata) -> Result<Lyric, LyricError>;
 }
 
 pub trait ProcessPolicy: Send + Sync {
     fn should_process(&self, path: &Path) -> Result<bool>;
+
+    /// Stores the outcome of a file that `should_process` let through.
+    fn record(&self, _record: &ProcessRecord) -> Result<()> {
+        Ok(())
+    }
 }
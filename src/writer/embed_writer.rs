@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use id3::frame::{Lyrics, SynchronisedLyrics, SynchronisedLyricsType, TimestampFormat};
+use id3::TagLike;
+use log::{error, warn};
+
+use crate::model::data_model::{AudioExtensions, Lyric, Writer};
+use crate::model::lrc::LrcDocument;
+
+impl Writer {
+    const ID3_LANGUAGE: &'static str = "eng";
+
+    /// Stores the lyric inside the audio file tags: ID3v2 `USLT`/`SYLT` for MP3,
+    /// `LYRICS`/`UNSYNCEDLYRICS` Vorbis comments for FLAC. Only the tag block is
+    /// rewritten, the audio frames are left untouched.
+    pub fn embed_lyric(lyric: &Lyric) -> Option<PathBuf> {
+        if lyric.lyric.is_empty() {
+            warn!("Lyrics were empty, skipping embed operation.");
+            return None;
+        }
+        let path = &lyric.song.filepath;
+        let document = lyric.document();
+        let result = match lyric.song.extension {
+            AudioExtensions::MP3 => Self::embed_id3(path, &document),
+            AudioExtensions::FLAC => Self::embed_flac(path, &document),
+            other => {
+                warn!("Embedding lyrics is not supported for {} files: {}", other, &lyric.song.filename);
+                return None;
+            }
+        };
+        if let Err(e) = result {
+            error!("Failed to embed lyrics into {}: {:#}", path.display(), e);
+            return None;
+        }
+        Some(path.to_owned())
+    }
+
+    fn embed_id3(path: &Path, document: &LrcDocument) -> Result<()> {
+        let existing = id3::no_tag_ok(id3::Tag::read_from_path(path))
+            .with_context(|| format!("Failed to read ID3 tag: {:?}", path))?;
+        let mut tag = existing.unwrap_or_default();
+        let version = tag.version();
+
+        tag.remove_all_lyrics();
+        tag.remove_all_synchronised_lyrics();
+        tag.add_frame(Lyrics {
+            lang: Self::ID3_LANGUAGE.to_owned(),
+            description: String::new(),
+            text: document.plain_text(),
+        });
+        if document.is_synced() {
+            let content = document
+                .synced_lines()
+                .into_iter()
+                .map(|(timestamp, text)| (timestamp.millis.min(u32::MAX as u64) as u32, text.trim().to_owned()))
+                .collect();
+            tag.add_frame(SynchronisedLyrics {
+                lang: Self::ID3_LANGUAGE.to_owned(),
+                timestamp_format: TimestampFormat::Ms,
+                content_type: SynchronisedLyricsType::Lyrics,
+                description: String::new(),
+                content,
+            });
+        }
+        tag.write_to_path(path, version)
+            .with_context(|| format!("Failed to write ID3 tag: {:?}", path))
+    }
+
+    fn embed_flac(path: &Path, document: &LrcDocument) -> Result<()> {
+        let mut tag = metaflac::Tag::read_from_path(path)
+            .with_context(|| format!("Failed to read FLAC metadata: {:?}", path))?;
+        let plain = document.plain_text();
+        let lyrics = if document.is_synced() { document.to_string() } else { plain.clone() };
+
+        tag.set_vorbis("LYRICS", vec![lyrics]);
+        tag.set_vorbis("UNSYNCEDLYRICS", vec![plain]);
+        tag.save()
+            .with_context(|| format!("Failed to write FLAC metadata: {:?}", path))
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use id3::TagLike;
+    use crate::model::data_model::{AudioExtensions, Lyric, Song, Writer};
+
+    const SYNCED: &str = "[ar:Benny Blanco]\n[00:01.00]First line\n[00:02.50]Second line";
+
+    fn lyric_for(path: PathBuf, extension: AudioExtensions, text: &str) -> Lyric {
+        Lyric {
+            lyric: String::from(text),
+            song: Song {
+                filename: path.file_name().unwrap().to_str().unwrap().to_string(),
+                extension,
+                filepath: path,
+            },
+        }
+    }
+
+    /// Everything after the leading ID3v2 tag, i.e. the audio stream.
+    fn audio_after_id3(bytes: &[u8]) -> &[u8] {
+        assert_eq!(&bytes[..3], b"ID3");
+        let size = bytes[6..10].iter().fold(0usize, |acc, byte| (acc << 7) | (*byte as usize & 0x7f));
+        &bytes[10 + size..]
+    }
+
+    /// The smallest file metaflac accepts: the marker, an empty STREAMINFO and fake frames.
+    fn minimal_flac(path: &Path) -> Vec<u8> {
+        let mut bytes = b"fLaC".to_vec();
+        bytes.extend([0x80, 0x00, 0x00, 0x22]);
+        bytes.extend([0u8; 34]);
+        bytes.extend(b"not really audio frames");
+        fs::write(path, &bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_embed_mp3_uslt_and_sylt() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let song_path = tmp_dir.path().join("roses.mp3");
+        fs::copy("test_resources/benny_blanco-roses.mp3", &song_path).unwrap();
+        let original = fs::read(&song_path).unwrap();
+
+        let result = Writer::embed_lyric(&lyric_for(song_path.clone(), AudioExtensions::MP3, SYNCED));
+        assert_eq!(result, Some(song_path.clone()));
+
+        let tag = id3::Tag::read_from_path(&song_path).unwrap();
+        let plain: Vec<&str> = tag.lyrics().map(|lyrics| lyrics.text.as_str()).collect();
+        assert_eq!(plain, vec!["First line\nSecond line"]);
+        let synced: Vec<_> = tag.synchronised_lyrics().flat_map(|lyrics| lyrics.content.clone()).collect();
+        assert_eq!(synced, vec![(1000, "First line".to_string()), (2500, "Second line".to_string())]);
+        assert_eq!(tag.title(), Some("Roses"));
+
+        let rewritten = fs::read(&song_path).unwrap();
+        assert_eq!(audio_after_id3(&original), audio_after_id3(&rewritten));
+
+        // Embedding again replaces the frames instead of piling them up.
+        Writer::embed_lyric(&lyric_for(song_path.clone(), AudioExtensions::MP3, "Plain only")).unwrap();
+        let tag = id3::Tag::read_from_path(&song_path).unwrap();
+        assert_eq!(tag.lyrics().count(), 1);
+        assert_eq!(tag.synchronised_lyrics().count(), 0);
+    }
+
+    #[test]
+    fn test_embed_flac_vorbis_comments() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let song_path = tmp_dir.path().join("song.flac");
+        let original = minimal_flac(&song_path);
+
+        Writer::embed_lyric(&lyric_for(song_path.clone(), AudioExtensions::FLAC, SYNCED)).unwrap();
+
+        let tag = metaflac::Tag::read_from_path(&song_path).unwrap();
+        let lyrics: Vec<&str> = tag.get_vorbis("LYRICS").unwrap().collect();
+        let unsynced: Vec<&str> = tag.get_vorbis("UNSYNCEDLYRICS").unwrap().collect();
+        assert_eq!(lyrics, vec![SYNCED]);
+        assert_eq!(unsynced, vec!["First line\nSecond line"]);
+
+        let rewritten = fs::read(&song_path).unwrap();
+        assert!(rewritten.ends_with(b"not really audio frames"));
+        assert!(rewritten.len() > original.len());
+    }
+
+    #[test]
+    fn test_embed_unsupported_format() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let song_path = tmp_dir.path().join("song.wav");
+        fs::write(&song_path, b"RIFF").unwrap();
+
+        let result = Writer::embed_lyric(&lyric_for(song_path, AudioExtensions::WAV, SYNCED));
+        assert!(result.is_none());
+    }
+}
@@ -0,0 +1,26 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::model::data_model::{ShiftMode, Writer};
+use crate::model::lrc::LrcDocument;
+
+impl Writer {
+    /// Shifts the `.lrc` file at `path` by `millis` in place. Everything but
+    /// the timestamps (or the `[offset:]` tag) is written back unchanged.
+    /// Returns `false` without touching files that have no synced lines.
+    pub fn shift_lrc_file(path: &Path, millis: i64, mode: ShiftMode) -> Result<bool> {
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut document = LrcDocument::parse(&text);
+        if !document.is_synced() {
+            return Ok(false);
+        }
+        match mode {
+            ShiftMode::Timestamps => document.shift(millis),
+            ShiftMode::OffsetTag => document.add_offset(millis),
+        }
+        fs::write(path, document.to_string()).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(true)
+    }
+}
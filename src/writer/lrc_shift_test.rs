@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod lrc_shift_tests {
+    use std::fs;
+
+    use crate::model::data_model::{ShiftMode, Writer};
+
+    const SYNCED: &str = "[ar:Artist]\r\n[00:01.00]One\r\n\r\n[00:02.500][01:00.00]Chorus\r\n# comment\r\n";
+
+    #[test]
+    fn test_shift_timestamps_keeps_the_rest_of_the_file() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("song.lrc");
+        fs::write(&path, SYNCED).unwrap();
+
+        assert!(Writer::shift_lrc_file(&path, 750, ShiftMode::Timestamps).unwrap());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[ar:Artist]\r\n[00:01.75]One\r\n\r\n[00:03.250][01:00.75]Chorus\r\n# comment\r\n"
+        );
+
+        assert!(Writer::shift_lrc_file(&path, -2000, ShiftMode::Timestamps).unwrap());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[ar:Artist]\r\n[00:00.00]One\r\n\r\n[00:01.250][00:58.75]Chorus\r\n# comment\r\n"
+        );
+    }
+
+    #[test]
+    fn test_shift_offset_tag() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("song.lrc");
+        fs::write(&path, SYNCED).unwrap();
+
+        assert!(Writer::shift_lrc_file(&path, 500, ShiftMode::OffsetTag).unwrap());
+        let shifted = fs::read_to_string(&path).unwrap();
+        assert_eq!(shifted, SYNCED.replace("[ar:Artist]\r\n", "[ar:Artist]\r\n[offset:-500]\r\n"));
+
+        assert!(Writer::shift_lrc_file(&path, -500, ShiftMode::OffsetTag).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), SYNCED);
+    }
+
+    #[test]
+    fn test_plain_files_are_left_alone() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("plain.lrc");
+        fs::write(&path, "just words\n").unwrap();
+
+        assert!(!Writer::shift_lrc_file(&path, 500, ShiftMode::Timestamps).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "just words\n");
+        assert!(Writer::shift_lrc_file(&tmp_dir.path().join("missing.lrc"), 1, ShiftMode::Timestamps).is_err());
+    }
+}
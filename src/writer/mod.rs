@@ -1,2 +1,9 @@
+#[allow(clippy::module_inception)]
 mod writer;
-mod writer_test;
\ No newline at end of file
+mod embed_writer;
+mod lrc_shift;
+pub mod subtitle_writer;
+mod writer_test;
+mod embed_writer_test;
+mod lrc_shift_test;
+mod subtitle_writer_test;
\ No newline at end of file
@@ -0,0 +1,132 @@
+use std::fs;
+use std::path::PathBuf;
+
+use log::{error, warn};
+
+use crate::model::data_model::{Lyric, SubtitleFormat, Writer};
+use crate::model::lrc::LrcDocument;
+
+/// A subtitle cue, in milliseconds.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Cue {
+    pub start: u64,
+    pub end: u64,
+    pub text: String,
+}
+
+impl Writer {
+    /// How long the last line stays on screen when the track length is unknown.
+    pub const LAST_CUE_MILLIS: u64 = LrcDocument::LAST_LINE_MILLIS;
+
+    /// Turns the synced lines into cues. Each line lasts until the next one
+    /// starts, the last one until the end of the track. Empty lines only end
+    /// the line before them. The `[offset:]` tag is applied.
+    pub fn cues(document: &LrcDocument, duration: Option<f64>) -> Vec<Cue> {
+        let offset = document.offset().unwrap_or(0);
+        let lines: Vec<(u64, String)> = document
+            .synced_lines()
+            .into_iter()
+            .map(|(timestamp, text)| ((timestamp.millis as i64 - offset).max(0) as u64, text))
+            .collect();
+        let track_end = duration.filter(|seconds| *seconds > 0.0).map(|seconds| (seconds * 1000.0) as u64);
+
+        let mut cues = Vec::new();
+        for (index, (start, text)) in lines.iter().enumerate() {
+            if text.is_empty() {
+                continue;
+            }
+            let end = match lines[index + 1..].iter().find(|(next, _)| next > start) {
+                Some((next, _)) => *next,
+                None => track_end
+                    .filter(|end| end > start)
+                    .unwrap_or(start + Self::LAST_CUE_MILLIS),
+            };
+            cues.push(Cue { start: *start, end, text: text.clone() });
+        }
+        cues
+    }
+
+    pub fn render_subtitles(cues: &[Cue], format: SubtitleFormat) -> String {
+        match format {
+            SubtitleFormat::Srt => cues
+                .iter()
+                .enumerate()
+                .map(|(index, cue)| {
+                    format!(
+                        "{}\n{} --> {}\n{}\n",
+                        index + 1,
+                        Self::clock(cue.start, ','),
+                        Self::clock(cue.end, ','),
+                        cue.text
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join("\n"),
+            SubtitleFormat::WebVtt => {
+                let mut vtt = String::from("WEBVTT\n");
+                for cue in cues {
+                    vtt.push_str(&format!(
+                        "\n{} --> {}\n{}\n",
+                        Self::clock(cue.start, '.'),
+                        Self::clock(cue.end, '.'),
+                        Self::escape(&cue.text, false)
+                    ));
+                }
+                vtt
+            }
+            SubtitleFormat::Ttml => {
+                let mut ttml = String::from(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                     <tt xmlns=\"http://www.w3.org/ns/ttml\">\n  <body>\n    <div>\n",
+                );
+                for cue in cues {
+                    ttml.push_str(&format!(
+                        "      <p begin=\"{}\" end=\"{}\">{}</p>\n",
+                        Self::clock(cue.start, '.'),
+                        Self::clock(cue.end, '.'),
+                        Self::escape(&cue.text, true)
+                    ));
+                }
+                ttml.push_str("    </div>\n  </body>\n</tt>\n");
+                ttml
+            }
+        }
+    }
+
+    /// Writes one subtitle file per format next to the song. `duration` is the
+    /// track length in seconds. Plain lyrics have no timings and are skipped.
+    pub fn write_subtitles(lyric: &Lyric, duration: Option<f64>, formats: &[SubtitleFormat]) -> Vec<PathBuf> {
+        let document = lyric.document();
+        if !document.is_synced() {
+            warn!("Lyrics for {} are not synced, skipping subtitles.", &lyric.song.filename);
+            return Vec::new();
+        }
+        let cues = Self::cues(&document, duration);
+        let mut written = Vec::new();
+        for format in formats {
+            let path = lyric.song.filepath.with_extension(format.extension());
+            match fs::write(&path, Self::render_subtitles(&cues, *format)) {
+                Ok(()) => written.push(path),
+                Err(e) => error!("Failed to write subtitle file {}: {}", path.display(), e),
+            }
+        }
+        written
+    }
+
+    /// `hh:mm:ss<separator>mmm`
+    fn clock(millis: u64, separator: char) -> String {
+        format!(
+            "{:02}:{:02}:{:02}{}{:03}",
+            millis / 3_600_000,
+            millis / 60_000 % 60,
+            millis / 1000 % 60,
+            separator,
+            millis % 1000
+        )
+    }
+
+    fn escape(text: &str, quotes: bool) -> String {
+        let text = text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+        if quotes { text.replace('"', "&quot;") } else { text }
+    }
+}
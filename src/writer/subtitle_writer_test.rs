@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod subtitle_writer_tests {
+    use std::fs;
+
+    use crate::model::data_model::{AudioExtensions, FieldSources, Lyric, Song, SongMetadata, SubtitleFormat, Writer, WriterOptions};
+    use crate::model::lrc::LrcDocument;
+    use crate::writer::subtitle_writer::Cue;
+
+    const SYNCED: &str = "[ti:Song]\n[00:01.00]First & <last>\n[00:03.50]\n[00:05.00][01:02.25]Chorus\n[00:08.00]Verse";
+
+    fn cue(start: u64, end: u64, text: &str) -> Cue {
+        Cue { start, end, text: text.to_owned() }
+    }
+
+    #[test]
+    fn test_cues_end_at_the_next_line_and_the_track() {
+        let document = LrcDocument::parse(SYNCED);
+        assert_eq!(
+            Writer::cues(&document, Some(70.0)),
+            vec![
+                cue(1000, 3500, "First & <last>"),
+                cue(5000, 8000, "Chorus"),
+                cue(8000, 62_250, "Verse"),
+                cue(62_250, 70_000, "Chorus"),
+            ]
+        );
+        let last = Writer::cues(&document, None).pop().unwrap();
+        assert_eq!(last.end, 62_250 + Writer::LAST_CUE_MILLIS);
+    }
+
+    #[test]
+    fn test_cues_apply_the_offset_tag() {
+        let document = LrcDocument::parse("[offset:+500]\n[00:00.20]a\n[00:02.00]b");
+        assert_eq!(Writer::cues(&document, Some(3.0)), vec![cue(0, 1500, "a"), cue(1500, 3000, "b")]);
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let cues = vec![cue(1000, 3500, "One & <two>"), cue(3_723_004, 3_725_000, "Three")];
+        assert_eq!(
+            Writer::render_subtitles(&cues, SubtitleFormat::Srt),
+            "1\n00:00:01,000 --> 00:00:03,500\nOne & <two>\n\n2\n01:02:03,004 --> 01:02:05,000\nThree\n"
+        );
+        assert_eq!(
+            Writer::render_subtitles(&cues, SubtitleFormat::WebVtt),
+            "WEBVTT\n\n00:00:01.000 --> 00:00:03.500\nOne &amp; &lt;two&gt;\n\n01:02:03.004 --> 01:02:05.000\nThree\n"
+        );
+        let ttml = Writer::render_subtitles(&cues, SubtitleFormat::Ttml);
+        assert!(ttml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<tt xmlns=\"http://www.w3.org/ns/ttml\">"));
+        assert!(ttml.contains("<p begin=\"00:00:01.000\" end=\"00:00:03.500\">One &amp; &lt;two&gt;</p>"));
+        assert!(ttml.ends_with("</tt>\n"));
+    }
+
+    #[test]
+    fn test_format_names() {
+        assert_eq!(
+            SubtitleFormat::parse_list("srt, WebVTT,ttml"),
+            Ok(vec![SubtitleFormat::Srt, SubtitleFormat::WebVtt, SubtitleFormat::Ttml])
+        );
+        assert!(SubtitleFormat::parse_list("srt,ass").is_err());
+    }
+
+    #[test]
+    fn test_write_outputs_adds_subtitles_for_synced_lyrics() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let song = Song {
+            filename: String::from("song.mp3"),
+            extension: AudioExtensions::MP3,
+            filepath: tmp_dir.path().join("song.mp3"),
+        };
+        let md = SongMetadata {
+            song: song.clone(),
+            artist: String::from("Artist"),
+            title: String::from("Song"),
+            album_title: String::new(),
+            duration: Some(70.0),
+            sources: FieldSources::default(),
+        };
+        let options = WriterOptions { subtitles: SubtitleFormat::ALL.to_vec(), ..WriterOptions::default() };
+
+        let synced = Lyric { lyric: SYNCED.to_owned(), song: song.clone() };
+        let written = Writer::write_outputs(&synced, &md, &options);
+        assert_eq!(written.len(), 4);
+        for extension in ["lrc", "srt", "vtt", "ttml"] {
+            assert!(tmp_dir.path().join(format!("song.{}", extension)).is_file(), "{}", extension);
+        }
+        assert!(fs::read_to_string(tmp_dir.path().join("song.srt")).unwrap().contains("Chorus"));
+
+        let plain = Lyric { lyric: String::from("Plain words"), song };
+        assert_eq!(Writer::write_outputs(&plain, &md, &options).len(), 1);
+    }
+}
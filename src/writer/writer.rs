@@ -1,22 +1,19 @@
 use std::fs;
-use std::path::PathBuf;
-use log::{error, warn};
-use crate::model::data_model::{Lyric, Writer};
+use std::path::{Path, PathBuf};
+use log::{error, info, warn};
+use crate::model::data_model::{Lyric, OverwritePolicy, Song, SongMetadata, Writer, WriterOptions};
+use crate::model::lrc::LrcDocument;
 
 impl Writer {
+    pub const CREATOR: &'static str = concat!("GetLyrics v", env!("CARGO_PKG_VERSION"));
+
     pub fn write_lyric(lyric: &Lyric) -> Option<PathBuf> {
-        let parent_filepath = lyric.song.filepath.parent()?;
         let lyrics = lyric.lyric.clone(); // or .to_owned()
         if lyrics.is_empty() {
             warn!("Lyrics were empty, skipping write operation.");
             return None
         }
-        let original_filename = lyric.song.filename.clone();
-
-        let mut new_filename = PathBuf::from(&original_filename);
-        new_filename.set_extension("lrc");
-
-        let full_path = parent_filepath.join(new_filename);
+        let full_path = Self::lrc_path(&lyric.song)?;
 
         // Write lyrics to the new file
         if let Err(e) = fs::write(&full_path, lyrics) {
@@ -25,4 +22,107 @@ impl Writer {
         }
         Some(full_path)
     }
+
+    /// The `.lrc` file next to the song.
+    pub fn lrc_path(song: &Song) -> Option<PathBuf> {
+        let mut new_filename = PathBuf::from(&song.filename);
+        new_filename.set_extension("lrc");
+        Some(song.filepath.parent()?.join(new_filename))
+    }
+
+    /// Whether `policy` lets `lyric` replace the `.lrc` file at `path`.
+    pub fn may_overwrite(path: &Path, lyric: &Lyric, policy: OverwritePolicy) -> bool {
+        if policy == OverwritePolicy::Always || !path.exists() {
+            return true;
+        }
+        match policy {
+            OverwritePolicy::Upgrade => {
+                let existing = fs::read_to_string(path).unwrap_or_default();
+                lyric.is_synced() && !LrcDocument::parse(&existing).is_synced()
+            }
+            _ => false,
+        }
+    }
+
+    /// Writes the lyric, adding an ID tag header built from the song metadata
+    /// when enabled in the options.
+    pub fn write_lyric_with_metadata(
+        lyric: &Lyric,
+        song_metadata: &SongMetadata,
+        options: &WriterOptions,
+    ) -> Option<PathBuf> {
+        if !options.id_tags || lyric.lyric.is_empty() {
+            return Self::write_lyric(lyric);
+        }
+        let document = Self::with_id_tags(lyric.document(), song_metadata);
+        Self::write_lyric(&Lyric {
+            lyric: document.to_string(),
+            song: lyric.song.clone(),
+        })
+    }
+
+    /// Writes every output enabled in the options and returns the paths
+    /// written. A `.lrc` file the overwrite policy keeps is returned as well.
+    pub fn write_outputs(lyric: &Lyric, song_metadata: &SongMetadata, options: &WriterOptions) -> Vec<PathBuf> {
+        let with_words;
+        let lyric = if options.word_timings && lyric.is_synced() {
+            with_words = Self::with_word_timings(lyric, song_metadata);
+            &with_words
+        } else {
+            lyric
+        };
+        let mut written = Vec::new();
+        if options.sidecar {
+            match Self::lrc_path(&lyric.song) {
+                Some(path) if !Self::may_overwrite(&path, lyric, options.overwrite) => {
+                    info!("Keeping existing {}", path.display());
+                    written.push(path);
+                }
+                _ => written.extend(Self::write_lyric_with_metadata(lyric, song_metadata, options)),
+            }
+        }
+        if options.embed {
+            written.extend(Self::embed_lyric(lyric));
+        }
+        if !options.subtitles.is_empty() {
+            written.extend(Self::write_subtitles(lyric, song_metadata.duration, &options.subtitles));
+        }
+        written
+    }
+
+    /// Adds approximate word times to the lines that have none. Word times
+    /// sent by the provider are kept.
+    pub fn with_word_timings(lyric: &Lyric, song_metadata: &SongMetadata) -> Lyric {
+        let mut document = lyric.document();
+        let track_end = song_metadata.duration.map(|seconds| (seconds * 1000.0) as u64);
+        document.synthesize_word_timings(track_end);
+        Lyric {
+            lyric: document.to_string(),
+            song: lyric.song.clone(),
+        }
+    }
+
+    /// Sets the standard ID tags, replacing any the provider already sent.
+    pub fn with_id_tags(mut document: LrcDocument, song_metadata: &SongMetadata) -> LrcDocument {
+        let tags = [
+            (LrcDocument::ARTIST, song_metadata.artist.clone()),
+            (LrcDocument::TITLE, song_metadata.title.clone()),
+            (LrcDocument::ALBUM, song_metadata.album_title.clone()),
+            (
+                LrcDocument::LENGTH,
+                song_metadata
+                    .duration
+                    .map(|seconds| seconds as u64)
+                    .map(|seconds| format!("{:02}:{:02}", seconds / 60, seconds % 60))
+                    .unwrap_or_default(),
+            ),
+            ("re", Self::CREATOR.to_owned()),
+        ];
+        for (key, value) in tags {
+            if !value.is_empty() {
+                document.set_tag(key, &value);
+            }
+        }
+        document
+    }
 }
\ No newline at end of file
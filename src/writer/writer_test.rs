@@ -2,7 +2,7 @@
 mod tests {
     use std::{env, fs};
     use std::path::PathBuf;
-    use crate::model::data_model::{Lyric, Song, AudioExtensions, Writer};
+    use crate::model::data_model::{Lyric, Song, AudioExtensions, SongMetadata, FieldSources, OverwritePolicy, Writer, WriterOptions};
 
     #[test]
     fn test_write_lyric_success() {
@@ -54,4 +54,95 @@ mod tests {
         let result = Writer::write_lyric(&lyric);
         assert!(result.is_none());
     }
+
+    fn song_in(dir: &std::path::Path) -> Song {
+        Song {
+            filename: "roses.mp3".to_string(),
+            extension: AudioExtensions::MP3,
+            filepath: dir.join("roses.mp3"),
+        }
+    }
+
+    fn metadata(song: &Song) -> SongMetadata {
+        SongMetadata {
+            song: song.clone(),
+            artist: String::from("Benny Blanco"),
+            title: String::from("Roses"),
+            album_title: String::new(),
+            duration: Some(223.0),
+            sources: FieldSources::default(),
+        }
+    }
+
+    #[test]
+    fn test_write_lyric_with_id_tags() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let song = song_in(tmp_dir.path());
+        let lyric = Lyric {
+            lyric: String::from("[ti:Wrong title]\n[00:01.00]Hello"),
+            song: song.clone(),
+        };
+
+        let output_path = Writer::write_lyric_with_metadata(&lyric, &metadata(&song), &WriterOptions::default()).unwrap();
+
+        let written_content = fs::read_to_string(output_path).unwrap();
+        let expected = format!("[ti:Roses]\n[ar:Benny Blanco]\n[length:03:43]\n[re:{}]\n[00:01.00]Hello", Writer::CREATOR);
+        assert_eq!(written_content, expected);
+    }
+
+    #[test]
+    fn test_write_lyric_without_id_tags() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let song = song_in(tmp_dir.path());
+        let lyric = Lyric {
+            lyric: String::from("Plain words"),
+            song: song.clone(),
+        };
+
+        let options = WriterOptions { id_tags: false, ..WriterOptions::default() };
+        let output_path = Writer::write_lyric_with_metadata(&lyric, &metadata(&song), &options).unwrap();
+        assert_eq!(fs::read_to_string(output_path).unwrap(), "Plain words");
+    }
+
+    #[test]
+    fn test_write_outputs_with_word_timings() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let song = song_in(tmp_dir.path());
+        let lyric = Lyric {
+            lyric: String::from("[03:40.00]Last words\n[03:41.00]<03:41.00>From <03:42.00>provider"),
+            song: song.clone(),
+        };
+
+        let options = WriterOptions { id_tags: false, word_timings: true, ..WriterOptions::default() };
+        let written = Writer::write_outputs(&lyric, &metadata(&song), &options);
+        assert_eq!(
+            fs::read_to_string(&written[0]).unwrap(),
+            "[03:40.00]<03:40.00>Last <03:40.44>words<03:41.00>\n[03:41.00]<03:41.00>From <03:42.00>provider"
+        );
+    }
+
+    #[test]
+    fn test_write_outputs_overwrite_policy() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let song = song_in(tmp_dir.path());
+        let md = metadata(&song);
+        let lrc_path = Writer::lrc_path(&song).unwrap();
+        let plain = Lyric { lyric: String::from("Plain line"), song: song.clone() };
+        let synced = Lyric { lyric: String::from("[00:01.00]Synced line"), song: song.clone() };
+        let options = |overwrite| WriterOptions { id_tags: false, overwrite, ..WriterOptions::default() };
+
+        fs::write(&lrc_path, "Edited by hand").unwrap();
+        assert_eq!(Writer::write_outputs(&synced, &md, &options(OverwritePolicy::Never)), vec![lrc_path.clone()]);
+        assert_eq!(fs::read_to_string(&lrc_path).unwrap(), "Edited by hand");
+
+        Writer::write_outputs(&plain, &md, &options(OverwritePolicy::Upgrade));
+        assert_eq!(fs::read_to_string(&lrc_path).unwrap(), "Edited by hand");
+        Writer::write_outputs(&synced, &md, &options(OverwritePolicy::Upgrade));
+        assert_eq!(fs::read_to_string(&lrc_path).unwrap(), "[00:01.00]Synced line");
+        Writer::write_outputs(&plain, &md, &options(OverwritePolicy::Upgrade));
+        assert_eq!(fs::read_to_string(&lrc_path).unwrap(), "[00:01.00]Synced line");
+
+        Writer::write_outputs(&plain, &md, &options(OverwritePolicy::Always));
+        assert_eq!(fs::read_to_string(&lrc_path).unwrap(), "Plain line");
+    }
 }
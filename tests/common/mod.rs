@@ -0,0 +1,140 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use GetLyrics::model::data_model::{FieldSources, Song, SongMetadata};
+
+/// A request as seen by the mock server.
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    /// Header names are lowercase.
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub body: String,
+    pub headers: Vec<(String, String)>,
+    /// How long the server stalls before answering.
+    pub delay: Option<Duration>,
+}
+
+impl MockResponse {
+    pub fn json(status: u16, body: &str) -> Self {
+        MockResponse {
+            status,
+            body: body.to_owned(),
+            headers: vec![("Content-Type".to_owned(), "application/json".to_owned())],
+            delay: None,
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+}
+
+type Handler = dyn Fn(&MockRequest) -> MockResponse + Send + Sync;
+
+/// A tiny in-process HTTP/1.1 server standing in for the lyric providers.
+/// It answers every request with the handler and records what it received.
+pub struct MockServer {
+    pub base_url: String,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockServer {
+    pub fn start(handler: impl Fn(&MockRequest) -> MockResponse + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind mock server");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let recorded = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().map_while(Result::ok) {
+                let handler = handler.clone();
+                let recorded = recorded.clone();
+                thread::spawn(move || Self::serve(stream, handler, recorded));
+            }
+        });
+
+        MockServer { base_url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn serve(stream: TcpStream, handler: Arc<Handler>, recorded: Arc<Mutex<Vec<MockRequest>>>) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).is_err() {
+            return;
+        }
+        // GET requests carry no body, so the request ends with its headers.
+        let mut headers = HashMap::new();
+        let mut header = String::new();
+        while reader.read_line(&mut header).map(|n| n > 2).unwrap_or(false) {
+            if let Some((name, value)) = header.split_once(':') {
+                headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_owned());
+            }
+            header.clear();
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_owned();
+        let target = parts.next().unwrap_or_default();
+        let url = reqwest::Url::parse(&format!("http://mock{}", target)).unwrap();
+        let request = MockRequest {
+            method,
+            path: url.path().to_owned(),
+            query: url.query_pairs().into_owned().collect(),
+            headers,
+        };
+        recorded.lock().unwrap().push(request.clone());
+
+        let response = handler(&request);
+        if let Some(delay) = response.delay {
+            thread::sleep(delay);
+        }
+        let mut stream = stream;
+        let mut head = format!(
+            "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+            response.status,
+            response.body.len()
+        );
+        for (name, value) in &response.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        let _ = stream.write_all(head.as_bytes());
+        let _ = stream.write_all(response.body.as_bytes());
+    }
+}
+
+pub fn song_metadata(artist: &str, title: &str, album: &str, duration: Option<f64>) -> SongMetadata {
+    SongMetadata {
+        song: Song::empty(),
+        artist: artist.to_owned(),
+        title: title.to_owned(),
+        album_title: album.to_owned(),
+        duration,
+        sources: FieldSources::default(),
+    }
+}
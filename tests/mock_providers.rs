@@ -0,0 +1,405 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use common::{song_metadata, MockResponse, MockServer};
+use GetLyrics::api::http_client::HttpOptions;
+use GetLyrics::api::lrclib_api::LrcLibAPI;
+use GetLyrics::api::lyric_api::LyricApi;
+use GetLyrics::api::lyric_error::LyricError;
+use GetLyrics::api::provider_chain::{ChainStrategy, ProviderChain, ProviderOptions};
+use GetLyrics::api::throttle::{RetryPolicy, Throttle, ThrottleOptions};
+use GetLyrics::store::lookup_cache::{CacheTtl, LookupCache};
+use GetLyrics::traits::traits::LyricIface;
+
+const LRCLIB_HIT: &str =
+    r#"{"instrumental":false,"plainLyrics":"Plain line","syncedLyrics":"[00:01.00] Synced line"}"#;
+
+fn lrclib(server: &MockServer, karaoke: bool) -> LrcLibAPI {
+    let api = if karaoke {
+        LrcLibAPI::new_karaoke_lyrics()
+    } else {
+        LrcLibAPI::new_plain_lyrics()
+    };
+    api.with_base_url(&format!("{}/api", server.base_url))
+}
+
+#[test]
+fn lrclib_sends_track_query_to_base_url() {
+    let server = MockServer::start(|_| MockResponse::json(200, LRCLIB_HIT));
+    let md = song_metadata("Benny Blanco", "Roses", "Roses", Some(223.0));
+
+    let lyric = lrclib(&server, false).fetch_lyrics(&md).unwrap();
+    assert_eq!(lyric.lyric, "Plain line");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[0].path, "/api/get");
+    assert_eq!(requests[0].query["track_name"], "Roses");
+    assert_eq!(requests[0].query["artist_name"], "Benny Blanco");
+    assert_eq!(requests[0].query["album_name"], "Roses");
+    assert_eq!(requests[0].query["duration"], "223");
+}
+
+#[test]
+fn lrclib_karaoke_prefers_synced_lyrics() {
+    let server = MockServer::start(|_| MockResponse::json(200, LRCLIB_HIT));
+    let md = song_metadata("Artist", "Title", "", None);
+
+    let lyric = lrclib(&server, true).fetch_lyrics(&md).unwrap();
+    assert_eq!(lyric.lyric, "[00:01.00] Synced line");
+}
+
+#[test]
+fn lrclib_accepts_synced_only_answers() {
+    let server = MockServer::start(|_| {
+        MockResponse::json(200, r#"{"instrumental":false,"plainLyrics":null,"syncedLyrics":"[00:01.00] Synced line"}"#)
+    });
+    let md = song_metadata("Artist", "Title", "", None);
+
+    assert_eq!(lrclib(&server, true).fetch_lyrics(&md).unwrap().lyric, "[00:01.00] Synced line");
+    assert_eq!(lrclib(&server, false).fetch_lyrics(&md).unwrap().lyric, "[00:01.00] Synced line");
+}
+
+#[test]
+fn lrclib_rejects_synced_only_answers_past_the_end() {
+    let server = MockServer::start(|request| match request.path.as_str() {
+        "/api/search" => MockResponse::json(200, "[]"),
+        _ => MockResponse::json(200, r#"{"instrumental":false,"plainLyrics":null,"syncedLyrics":"[03:30.00] Late line"}"#),
+    });
+    let md = song_metadata("Artist", "Title", "", Some(200.0));
+
+    assert_eq!(lrclib(&server, true).fetch_lyrics(&md).unwrap_err(), LyricError::NotFound);
+    assert_eq!(lrclib(&server, false).fetch_lyrics(&md).unwrap_err(), LyricError::NotFound);
+}
+
+#[test]
+fn lrclib_maps_errors() {
+    let server = MockServer::start(|request| match request.query["track_name"].as_str() {
+        "missing" => MockResponse::json(404, r#"{"message":"not found"}"#),
+        "instrumental" => MockResponse::json(200, r#"{"instrumental":true,"plainLyrics":null,"syncedLyrics":null}"#),
+        "limited" => MockResponse::json(429, "{}").with_header("Retry-After", "30"),
+        "broken" => MockResponse::json(200, "{not json"),
+        _ => MockResponse::json(502, "{}"),
+    });
+    let api = lrclib(&server, false);
+    let fetch = |title: &str| api.fetch_lyrics(&song_metadata("Artist", title, "", None)).unwrap_err();
+
+    assert_eq!(fetch("missing"), LyricError::NotFound);
+    assert_eq!(fetch("instrumental"), LyricError::Instrumental);
+    assert_eq!(fetch("limited"), LyricError::RateLimited { retry_after: Some(Duration::from_secs(30)) });
+    assert!(matches!(fetch("broken"), LyricError::Parse(_)));
+    assert_eq!(fetch("other"), LyricError::Upstream { status: 502 });
+}
+
+#[test]
+fn lyrics_ovh_uses_encoded_path_segments() {
+    let server = MockServer::start(|_| MockResponse::json(200, r#"{"lyrics":"Line one\r\nLine   two\n\n\n"}"#));
+    let api = LyricApi::new().with_base_url(&format!("{}/v1/", server.base_url));
+
+    let lyric = api.fetch_lyrics(&song_metadata("AC/DC", "T.N.T?", "", None)).unwrap();
+    assert_eq!(lyric.lyric, "Line one\nLine two");
+    assert_eq!(server.requests()[0].path, "/v1/AC%2FDC/T.N.T%3F");
+}
+
+#[test]
+fn lyrics_ovh_not_found() {
+    let server = MockServer::start(|_| MockResponse::json(404, r#"{"error":"No lyrics found"}"#));
+    let api = LyricApi::new().with_base_url(&format!("{}/v1", server.base_url));
+
+    let error = api.fetch_lyrics(&song_metadata("Artist", "Title", "", None)).unwrap_err();
+    assert_eq!(error, LyricError::NotFound);
+}
+
+#[test]
+fn unreachable_server_is_a_network_error() {
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let api = LrcLibAPI::new_plain_lyrics().with_base_url(&format!("http://127.0.0.1:{}/api", port));
+
+    let error = api.fetch_lyrics(&song_metadata("Artist", "Title", "", None)).unwrap_err();
+    assert!(matches!(error, LyricError::Network(_)));
+    assert!(error.is_retryable());
+}
+
+#[test]
+fn stalled_server_times_out() {
+    let server = MockServer::start(|_| MockResponse::json(200, LRCLIB_HIT).with_delay(Duration::from_secs(2)));
+    let client = HttpOptions::default().with_timeout(Duration::from_millis(200)).build().unwrap();
+    let api = lrclib(&server, false).with_client(client);
+
+    let error = api.fetch_lyrics(&song_metadata("Artist", "Title", "", None)).unwrap_err();
+    assert!(matches!(error, LyricError::Network(_)));
+}
+
+fn retrying(max_retries: u32) -> Throttle {
+    let retry = RetryPolicy {
+        max_retries,
+        base_delay: Duration::from_millis(10),
+        max_delay: Duration::from_secs(5),
+    };
+    Throttle::new(&ThrottleOptions::unlimited().with_retry(retry))
+}
+
+/// Answers with `failure` the first `failures` times, then with a hit.
+fn flaky_server(failures: usize, failure: MockResponse) -> MockServer {
+    let calls = Arc::new(AtomicUsize::new(0));
+    MockServer::start(move |_| {
+        if calls.fetch_add(1, Ordering::SeqCst) < failures {
+            failure.clone()
+        } else {
+            MockResponse::json(200, LRCLIB_HIT)
+        }
+    })
+}
+
+#[test]
+fn transient_failures_are_retried() {
+    let server = flaky_server(2, MockResponse::json(503, "{}"));
+    let api = lrclib(&server, false).with_throttle(retrying(3));
+
+    let lyric = api.fetch_lyrics(&song_metadata("Artist", "Title", "", None)).unwrap();
+    assert_eq!(lyric.lyric, "Plain line");
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn retries_give_up_with_the_last_error() {
+    let server = flaky_server(usize::MAX, MockResponse::json(503, "{}"));
+    let api = lrclib(&server, false).with_throttle(retrying(2));
+
+    let error = api.fetch_lyrics(&song_metadata("Artist", "Title", "", None)).unwrap_err();
+    assert_eq!(error, LyricError::Upstream { status: 503 });
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn retry_after_is_honored() {
+    let server = flaky_server(1, MockResponse::json(429, "{}").with_header("Retry-After", "1"));
+    let api = lrclib(&server, false).with_throttle(retrying(1));
+
+    let start = Instant::now();
+    api.fetch_lyrics(&song_metadata("Artist", "Title", "", None)).unwrap();
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn requests_are_rate_limited() {
+    let server = MockServer::start(|_| MockResponse::json(200, r#"{"lyrics":"Line"}"#));
+    let throttle = Throttle::new(&ThrottleOptions::unlimited().with_rate(10.0).unwrap());
+    let api = LyricApi::new().with_base_url(&format!("{}/v1", server.base_url)).with_throttle(throttle);
+
+    let start = Instant::now();
+    for _ in 0..13 {
+        api.fetch_lyrics(&song_metadata("Artist", "Title", "", None)).unwrap();
+    }
+    // A burst of 10, then 10 per second.
+    assert!(start.elapsed() >= Duration::from_millis(250), "took {:?}", start.elapsed());
+}
+
+#[test]
+fn cached_chain_answers_reruns_without_requests() {
+    let server = MockServer::start(|request| match request.query.get("track_name").map(String::as_str) {
+        Some("Known") => MockResponse::json(200, LRCLIB_HIT),
+        _ => MockResponse::json(404, "{}"),
+    });
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let cache = Arc::new(LookupCache::open(&tmp_dir.path().join(LookupCache::FILENAME)).unwrap());
+    let mut options = ProviderOptions::new(false);
+    options.set_base_url(LrcLibAPI::NAME, &format!("{}/api", server.base_url));
+    let chain = || {
+        ProviderChain::from_names(&[LrcLibAPI::NAME], ChainStrategy::FirstSuccess, &options)
+            .unwrap()
+            .with_cache(cache.clone(), CacheTtl::default(), "plain")
+    };
+
+    for _ in 0..2 {
+        let run = chain();
+        assert_eq!(run.fetch(&song_metadata("Artist", "Known", "", None)).unwrap().lyric.lyric, "Plain line");
+        assert_eq!(run.fetch(&song_metadata("Artist", "Unknown", "", None)).unwrap_err(), LyricError::NotFound);
+    }
+    // One get for the hit, a get and a search for the miss, all from the first run.
+    assert_eq!(server.requests().len(), 3);
+    assert_eq!(cache.all().unwrap().len(), 2);
+}
+
+#[test]
+fn chain_providers_share_the_configured_client() {
+    let server = MockServer::start(|request| {
+        if request.path.starts_with("/api/") {
+            MockResponse::json(404, "{}")
+        } else {
+            MockResponse::json(200, r#"{"lyrics":"From the fallback"}"#)
+        }
+    });
+    let mut options = ProviderOptions::new(false);
+    options.set_base_url(LrcLibAPI::NAME, &format!("{}/api", server.base_url));
+    options.set_base_url(LyricApi::NAME, &format!("{}/v1", server.base_url));
+    options.http = HttpOptions::default().with_user_agent("Library Test/1.0");
+
+    ProviderChain::default_chain(&options)
+        .unwrap()
+        .fetch(&song_metadata("Artist", "Title", "", None))
+        .unwrap();
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert!(requests.iter().all(|request| request.headers["user-agent"] == "Library Test/1.0"));
+}
+
+#[test]
+fn chain_falls_back_to_second_provider() {
+    let server = MockServer::start(|request| {
+        if request.path.starts_with("/api/") {
+            MockResponse::json(404, "{}")
+        } else {
+            MockResponse::json(200, r#"{"lyrics":"From the fallback"}"#)
+        }
+    });
+    let mut options = ProviderOptions::new(false);
+    options.set_base_url(LrcLibAPI::NAME, &format!("{}/api", server.base_url));
+    options.set_base_url(LyricApi::NAME, &format!("{}/v1", server.base_url));
+
+    let found = ProviderChain::default_chain(&options)
+        .unwrap()
+        .fetch(&song_metadata("Artist", "Title", "", None))
+        .unwrap();
+    assert_eq!(found.provider, LyricApi::NAME);
+    assert_eq!(found.lyric.lyric, "From the fallback");
+    // LRCLib get, LRCLib search, then lyrics.ovh.
+    assert_eq!(server.requests().len(), 3);
+}
+
+const SEARCH_RESULTS: &str = r#"[
+    {"trackName":"Hello","artistName":"Adele","duration":295.0,"instrumental":false,"plainLyrics":"Wrong song","syncedLyrics":null},
+    {"trackName":"Halo","artistName":"Beyoncé","duration":261.0,"instrumental":false,"plainLyrics":"Right song","syncedLyrics":"[00:01.00] Right song"}
+]"#;
+
+fn search_server() -> MockServer {
+    MockServer::start(|request| match request.path.as_str() {
+        "/api/get" => MockResponse::json(404, "{}"),
+        _ => MockResponse::json(200, SEARCH_RESULTS),
+    })
+}
+
+#[test]
+fn lrclib_search_accepts_best_candidate() {
+    let server = search_server();
+    let md = song_metadata("Beyonce", "Halo", "I Am... Sasha Fierce", Some(261.0));
+
+    let lyric = lrclib(&server, true).fetch_lyrics(&md).unwrap();
+    assert_eq!(lyric.lyric, "[00:01.00] Right song");
+
+    let requests = server.requests();
+    assert_eq!(requests[1].path, "/api/search");
+    assert_eq!(requests[1].query["track_name"], "Halo");
+    assert_eq!(requests[1].query["artist_name"], "Beyonce");
+}
+
+#[test]
+fn lrclib_search_rejects_candidates_below_threshold() {
+    let server = search_server();
+    let md = song_metadata("Beyonce", "Halo", "", Some(261.0));
+
+    let error = lrclib(&server, false).with_search_threshold(0.99).fetch_lyrics(&md).unwrap_err();
+    assert_eq!(error, LyricError::NotFound);
+
+    let md = song_metadata("Someone Else", "Another Song", "", Some(100.0));
+    assert_eq!(lrclib(&server, false).fetch_lyrics(&md).unwrap_err(), LyricError::NotFound);
+}
+
+#[test]
+fn lrclib_search_can_be_disabled() {
+    let server = search_server();
+    let md = song_metadata("Beyonce", "Halo", "", Some(261.0));
+
+    let error = lrclib(&server, false).without_search().fetch_lyrics(&md).unwrap_err();
+    assert_eq!(error, LyricError::NotFound);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn lrclib_retries_with_relaxed_queries() {
+    let server = MockServer::start(|request| match (request.path.as_str(), request.query["track_name"].as_str()) {
+        ("/api/get", "Roses") => MockResponse::json(200, LRCLIB_HIT),
+        _ => MockResponse::json(404, "{}"),
+    });
+    let md = song_metadata("Benny Blanco feat. Juice WRLD", "Roses (feat. SAINt JHN) [Live]", "", Some(223.0));
+
+    let lyric = lrclib(&server, false).fetch_lyrics(&md).unwrap();
+    assert_eq!(lyric.lyric, "Plain line");
+
+    let requests = server.requests();
+    let titles: Vec<&str> = requests.iter().map(|r| r.query["track_name"].as_str()).collect();
+    assert_eq!(titles, vec!["Roses (feat. SAINt JHN) [Live]", "Roses [Live]", "Roses"]);
+    assert_eq!(requests[2].query["artist_name"], "Benny Blanco");
+}
+
+#[test]
+fn lyrics_ovh_retries_with_primary_artist() {
+    let server = MockServer::start(|request| match request.path.as_str() {
+        "/v1/Simon/The%20Boxer" => MockResponse::json(200, r#"{"lyrics":"I am just a poor boy"}"#),
+        _ => MockResponse::json(404, r#"{"error":"No lyrics found"}"#),
+    });
+    let md = song_metadata("Simon & Garfunkel", "The Boxer", "", None);
+
+    let api = LyricApi::new().with_base_url(&format!("{}/v1", server.base_url));
+    assert_eq!(api.fetch_lyrics(&md).unwrap().lyric, "I am just a poor boy");
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn lrclib_omits_unknown_duration() {
+    let server = MockServer::start(|_| MockResponse::json(200, LRCLIB_HIT));
+    let md = song_metadata("Benny Blanco", "Roses", "Roses", None);
+    lrclib(&server, false).fetch_lyrics(&md).unwrap();
+    assert!(!server.requests()[0].query.contains_key("duration"));
+
+    let md = song_metadata("Benny Blanco", "Roses", "Roses", Some(222.6));
+    lrclib(&server, false).fetch_lyrics(&md).unwrap();
+    assert_eq!(server.requests()[1].query["duration"], "223");
+}
+
+#[test]
+fn lrclib_search_rejects_candidates_outside_duration_tolerance() {
+    let server = search_server();
+    let md = song_metadata("Beyonce", "Halo", "", Some(266.0));
+    let error = lrclib(&server, false).fetch_lyrics(&md).unwrap_err();
+    assert_eq!(error, LyricError::NotFound);
+
+    let lyric = lrclib(&server, false).with_duration_tolerance(5.0).fetch_lyrics(&md).unwrap();
+    assert_eq!(lyric.lyric, "Right song");
+}
+
+#[test]
+fn lrclib_search_scores_with_the_configured_duration_tolerance() {
+    let server = MockServer::start(|request| match request.path.as_str() {
+        "/api/get" => MockResponse::json(404, "{}"),
+        _ => MockResponse::json(
+            200,
+            r#"[{"trackName":"Halo","artistName":"Beyonce","duration":265.0,"instrumental":false,"plainLyrics":"Halo","syncedLyrics":null}]"#,
+        ),
+    });
+    let md = song_metadata("Beyonce", "Halo", "", Some(261.0));
+    let api = lrclib(&server, false).with_search_threshold(0.99).with_duration_tolerance(4.0);
+
+    // Four seconds off would lose part of its duration score with the default tolerance.
+    assert_eq!(api.fetch_lyrics(&md).unwrap().lyric, "Halo");
+}
+
+#[test]
+fn lrclib_rejects_synced_lyrics_longer_than_the_track() {
+    let server = MockServer::start(|_| {
+        MockResponse::json(
+            200,
+            r#"{"instrumental":false,"plainLyrics":"Plain line","syncedLyrics":"[00:01.00] First\n[03:50.00] Last"}"#,
+        )
+    });
+    let short_edit = song_metadata("Artist", "Title", "", Some(200.5));
+    assert_eq!(lrclib(&server, true).fetch_lyrics(&short_edit).unwrap().lyric, "Plain line");
+
+    let full_length = song_metadata("Artist", "Title", "", Some(229.0));
+    assert!(lrclib(&server, true).fetch_lyrics(&full_length).unwrap().lyric.starts_with("[00:01.00]"));
+}